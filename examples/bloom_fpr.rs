@@ -0,0 +1,25 @@
+//! Measures the false positive rate a `BloomFilter` actually delivers.
+//!
+//! cargo run --release --example bloom_fpr -- [capacity] [p] [load] [probes]
+//!
+//! The filter is filled to `load` times its capacity, then probed with keys that were never
+//! inserted.
+use std::env;
+use data_structures::bloom_filter::BloomFilter;
+use data_structures::set::fpr;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let arg = |i: usize, default: &str| args.get(i).cloned().unwrap_or_else(|| default.to_string());
+    let capacity: usize = arg(0, "100000").parse().expect("capacity must be an integer");
+    let p: f64 = arg(1, "0.01").parse().expect("p must be a number");
+    let load: f64 = arg(2, "1.0").parse().expect("load must be a number");
+    let probes: u64 = arg(3, "2000000").parse().expect("probes must be an integer");
+
+    let mut filter = BloomFilter::new(capacity, p);
+    print!("{filter}");
+    let inserts = (capacity as f64 * load) as u64;
+    // Inserted and probed keys come from disjoint id ranges, so every probe is absent
+    let measurement = fpr::measure(&mut filter, inserts, probes, 32, |id| format!("key-{id}"));
+    print!("{measurement}");
+}
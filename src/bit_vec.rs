@@ -0,0 +1,408 @@
+use std::fmt::{Debug, Formatter};
+use std::ops::Range;
+use std::sync::OnceLock;
+use crate::Error;
+
+const WORD_BITS: usize = 64;
+// Words per rank superblock
+const SUPERBLOCK_WORDS: usize = 8;
+const SUPERBLOCK_BITS: usize = SUPERBLOCK_WORDS * WORD_BITS;
+// Every SELECT_SAMPLE'th one (and zero) records the superblock it falls in
+const SELECT_SAMPLE: usize = 512;
+
+// Auxiliary rank/select directory, rebuilt on demand after the bits change.
+struct Index {
+    // Ones before the start of each superblock, followed by the total
+    superblocks: Vec<usize>,
+    // Superblock holding the (i * SELECT_SAMPLE)'th one or zero
+    one_samples: Vec<usize>,
+    zero_samples: Vec<usize>,
+}
+
+/// A fixed-length vector of bits packed into `u64` words, with constant-time `rank` and
+/// near-constant-time `select`.
+///
+/// The rank/select directory costs roughly 1/64th of the bits. It is built by the first query
+/// after a modification, so batch writes before querying.
+pub struct BitVec {
+    // Bits past `len` in the last word are always zero
+    words: Vec<u64>,
+    len: usize,
+    index: OnceLock<Index>,
+}
+
+impl BitVec {
+    /// `len` zeroed bits.
+    pub fn new(len: usize) -> Self {
+        BitVec {
+            words: vec![0; len.div_ceil(WORD_BITS)],
+            len,
+            index: OnceLock::new(),
+        }
+    }
+    /// The first `len` bits of `words`, least significant bit first.
+    pub fn from_words(mut words: Vec<u64>, len: usize) -> crate::Result<Self> {
+        if words.len() * WORD_BITS < len {
+            return Err(Error::IllegalArguments(format!("{} words cannot hold {len} bits.", words.len())));
+        }
+        words.truncate(len.div_ceil(WORD_BITS));
+        let mut bits = BitVec { words, len, index: OnceLock::new() };
+        bits.clear_padding();
+        Ok(bits)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        self.check(index);
+        self.words[index / WORD_BITS] & (1 << (index % WORD_BITS)) != 0
+    }
+    /// Sets the bit at `index`, returning its previous value.
+    pub fn set(&mut self, index: usize, value: bool) -> bool {
+        self.check(index);
+        let (word, mask) = (index / WORD_BITS, 1 << (index % WORD_BITS));
+        let previous = self.words[word] & mask != 0;
+        if previous != value {
+            self.words[word] ^= mask;
+            self.index.take();
+        }
+        previous
+    }
+    pub fn push(&mut self, value: bool) {
+        if self.len.is_multiple_of(WORD_BITS) {
+            self.words.push(0);
+        }
+        self.len += 1;
+        self.index.take();
+        self.set(self.len - 1, value);
+    }
+    pub fn fill(&mut self, value: bool) {
+        self.words.fill(if value { u64::MAX } else { 0 });
+        self.clear_padding();
+        self.index.take();
+    }
+
+    pub fn count_ones(&self) -> usize {
+        match self.index.get() {
+            Some(index) => index.superblocks[index.superblocks.len() - 1],
+            None => self.words.iter().map(|w| w.count_ones() as usize).sum(),
+        }
+    }
+    pub fn count_zeros(&self) -> usize {
+        self.len - self.count_ones()
+    }
+    /// Number of ones before `index`.
+    pub fn rank1(&self, index: usize) -> usize {
+        assert!(index <= self.len, "Rank index {index} out of range for {} bits", self.len);
+        let directory = self.index();
+        let word = index / WORD_BITS;
+        let superblock = index / SUPERBLOCK_BITS;
+        let mut rank = directory.superblocks[superblock];
+        for w in superblock * SUPERBLOCK_WORDS..word {
+            rank += self.words[w].count_ones() as usize;
+        }
+        let bit = index % WORD_BITS;
+        if bit > 0 {
+            rank += (self.words[word] & ((1 << bit) - 1)).count_ones() as usize;
+        }
+        rank
+    }
+    /// Number of zeros before `index`.
+    pub fn rank0(&self, index: usize) -> usize {
+        index - self.rank1(index)
+    }
+    /// Position of the `k`th one, counting from zero.
+    pub fn select1(&self, k: usize) -> Option<usize> {
+        if k >= self.count_ones() {
+            return None;
+        }
+        let directory = self.index();
+        Some(self.select(k, &directory.one_samples, |sb| directory.superblocks[sb], |w| w))
+    }
+    /// Position of the `k`th zero, counting from zero.
+    pub fn select0(&self, k: usize) -> Option<usize> {
+        if k >= self.count_zeros() {
+            return None;
+        }
+        let directory = self.index();
+        let zeros_before = |sb: usize| sb * SUPERBLOCK_BITS - directory.superblocks[sb];
+        Some(self.select(k, &directory.zero_samples, zeros_before, |w| !w))
+    }
+
+    pub fn and(&mut self, other: &BitVec) -> crate::Result<()> {
+        self.combine(other, |w, o| w & o)
+    }
+    pub fn or(&mut self, other: &BitVec) -> crate::Result<()> {
+        self.combine(other, |w, o| w | o)
+    }
+    pub fn xor(&mut self, other: &BitVec) -> crate::Result<()> {
+        self.combine(other, |w, o| w ^ o)
+    }
+    pub fn and_not(&mut self, other: &BitVec) -> crate::Result<()> {
+        self.combine(other, |w, o| w & !o)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len).map(|i| self.get(i))
+    }
+    /// Positions of the set bits, in ascending order.
+    pub fn iter_ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            let mut remaining = word;
+            std::iter::from_fn(move || {
+                if remaining == 0 {
+                    return None;
+                }
+                let bit = remaining.trailing_zeros() as usize;
+                remaining &= remaining - 1;
+                Some(i * WORD_BITS + bit)
+            })
+        })
+    }
+
+    fn check(&self, index: usize) {
+        assert!(index < self.len, "Bit index {index} out of range for {} bits", self.len);
+    }
+    fn clear_padding(&mut self) {
+        let used = self.len % WORD_BITS;
+        if used > 0 {
+            if let Some(last) = self.words.last_mut() {
+                *last &= (1 << used) - 1;
+            }
+        }
+    }
+    fn combine<F: Fn(u64, u64) -> u64>(&mut self, other: &BitVec, op: F) -> crate::Result<()> {
+        if self.len != other.len {
+            return Err(Error::IllegalArguments(format!("Bit vectors differ in length: {} and {}.", self.len, other.len)));
+        }
+        self.words.iter_mut().zip(&other.words).for_each(|(w, &o)| *w = op(*w, o));
+        self.clear_padding();
+        self.index.take();
+        Ok(())
+    }
+    fn index(&self) -> &Index {
+        self.index.get_or_init(|| {
+            let num_superblocks = self.words.len().div_ceil(SUPERBLOCK_WORDS);
+            let mut superblocks = Vec::with_capacity(num_superblocks + 1);
+            let mut one_samples = Vec::new();
+            let mut zero_samples = Vec::new();
+            let mut ones = 0;
+            for sb in 0..num_superblocks {
+                superblocks.push(ones);
+                let end = ((sb + 1) * SUPERBLOCK_WORDS).min(self.words.len());
+                ones += self.words[sb * SUPERBLOCK_WORDS..end].iter().map(|w| w.count_ones() as usize).sum::<usize>();
+                let zeros = (sb + 1) * SUPERBLOCK_BITS - ones;
+                while one_samples.len() * SELECT_SAMPLE < ones {
+                    one_samples.push(sb);
+                }
+                while zero_samples.len() * SELECT_SAMPLE < zeros {
+                    zero_samples.push(sb);
+                }
+            }
+            superblocks.push(ones);
+            Index { superblocks, one_samples, zero_samples }
+        })
+    }
+    // Narrows to a superblock between two samples, then scans at most one superblock of words.
+    fn select<C, W>(&self, k: usize, samples: &[usize], before: C, word: W) -> usize
+        where
+            C: Fn(usize) -> usize,
+            W: Fn(u64) -> u64,
+    {
+        let sample = k / SELECT_SAMPLE;
+        let low = samples[sample];
+        let high = samples.get(sample + 1).copied().unwrap_or(self.words.len().div_ceil(SUPERBLOCK_WORDS) - 1);
+        // The last superblock in low..=high starting at or before the kth bit holds it
+        let superblock = partition_point(low + 1..high + 1, |sb| before(sb) <= k) - 1;
+
+        let mut remaining = k - before(superblock);
+        let mut w = superblock * SUPERBLOCK_WORDS;
+        loop {
+            let mut bits = word(self.words[w]);
+            let count = bits.count_ones() as usize;
+            if remaining < count {
+                for _ in 0..remaining {
+                    bits &= bits - 1;
+                }
+                return w * WORD_BITS + bits.trailing_zeros() as usize;
+            }
+            remaining -= count;
+            w += 1;
+        }
+    }
+}
+
+// Like `slice::partition_point`, over the integers of `range`: the first one failing `pred`,
+// or `range.end` if all pass. `pred` must hold for a prefix of `range` and fail for the rest.
+fn partition_point<P: Fn(usize) -> bool>(range: Range<usize>, pred: P) -> usize {
+    let (mut low, mut high) = (range.start, range.end);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if pred(mid) {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
+impl Clone for BitVec {
+    fn clone(&self) -> Self {
+        BitVec {
+            words: self.words.clone(),
+            len: self.len,
+            index: OnceLock::new(),
+        }
+    }
+}
+
+impl PartialEq for BitVec {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.words == other.words
+    }
+}
+
+impl Eq for BitVec {}
+
+impl Debug for BitVec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for bit in self.iter() {
+            f.write_str(if bit { "1" } else { "0" })?;
+        }
+        Ok(())
+    }
+}
+
+impl FromIterator<bool> for BitVec {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        let mut bits = BitVec::new(0);
+        for bit in iter {
+            bits.push(bit);
+        }
+        bits
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use super::{BitVec, SUPERBLOCK_BITS};
+
+    #[test]
+    fn test_get_set() {
+        let mut bits = BitVec::new(130);
+        assert!(!bits.set(0, true));
+        assert!(!bits.set(64, true));
+        assert!(!bits.set(129, true));
+        assert!(bits.set(129, true));
+        assert!(bits.get(0) && bits.get(64) && bits.get(129));
+        assert!(!bits.get(1));
+        assert_eq!(bits.count_ones(), 3);
+        assert_eq!(bits.iter_ones().collect::<Vec<_>>(), vec![0, 64, 129]);
+
+        bits.set(64, false);
+        assert_eq!(bits.count_zeros(), 128);
+        bits.fill(true);
+        assert_eq!(bits.count_ones(), 130);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_get_out_of_range() {
+        BitVec::new(10).get(10);
+    }
+
+    #[test]
+    fn test_rank_select_match_naive() {
+        let mut rng = StdRng::seed_from_u64(31);
+        for density in [0.01, 0.5, 0.99] {
+            let bits: BitVec = (0..10_000).map(|_| rng.gen_bool(density)).collect();
+            let ones: Vec<usize> = (0..bits.len()).filter(|&i| bits.get(i)).collect();
+            let zeros: Vec<usize> = (0..bits.len()).filter(|&i| !bits.get(i)).collect();
+
+            for i in (0..=bits.len()).step_by(7) {
+                assert_eq!(bits.rank1(i), ones.partition_point(|&p| p < i));
+                assert_eq!(bits.rank0(i), zeros.partition_point(|&p| p < i));
+            }
+            for (k, &position) in ones.iter().enumerate() {
+                assert_eq!(bits.select1(k), Some(position));
+            }
+            for (k, &position) in zeros.iter().enumerate() {
+                assert_eq!(bits.select0(k), Some(position));
+            }
+            assert_eq!(bits.select1(ones.len()), None);
+            assert_eq!(bits.select0(zeros.len()), None);
+        }
+    }
+
+    #[test]
+    fn test_select_sparse() {
+        // Samples of ones lie thousands of superblocks apart, as do samples of zeros once flipped
+        let gap = 5 * SUPERBLOCK_BITS + 3;
+        let mut bits = BitVec::new(2_000 * gap);
+        for k in 0..2_000 {
+            bits.set(k * gap + k % 64, true);
+        }
+        for k in 0..2_000 {
+            assert_eq!(bits.select1(k), Some(k * gap + k % 64));
+        }
+        assert_eq!(bits.select1(2_000), None);
+
+        bits.fill(true);
+        for k in 0..2_000 {
+            bits.set(k * gap + k % 64, false);
+        }
+        for k in (0..2_000).step_by(3) {
+            assert_eq!(bits.select0(k), Some(k * gap + k % 64));
+        }
+    }
+
+    #[test]
+    fn test_index_rebuilt_after_modification() {
+        let mut bits = BitVec::new(1_000);
+        bits.set(500, true);
+        assert_eq!(bits.rank1(1_000), 1);
+        bits.set(10, true);
+        assert_eq!(bits.rank1(1_000), 2);
+        assert_eq!(bits.select1(0), Some(10));
+    }
+
+    #[test]
+    fn test_bulk_operations() {
+        let a: BitVec = [true, true, false, false].into_iter().collect();
+        let b: BitVec = [true, false, true, false].into_iter().collect();
+
+        let mut and = a.clone();
+        and.and(&b).unwrap();
+        assert_eq!(format!("{and:?}"), "1000");
+        let mut or = a.clone();
+        or.or(&b).unwrap();
+        assert_eq!(format!("{or:?}"), "1110");
+        let mut xor = a.clone();
+        xor.xor(&b).unwrap();
+        assert_eq!(format!("{xor:?}"), "0110");
+        let mut and_not = a.clone();
+        and_not.and_not(&b).unwrap();
+        assert_eq!(format!("{and_not:?}"), "0100");
+
+        assert!(and.or(&BitVec::new(5)).is_err());
+    }
+
+    #[test]
+    fn test_from_words() {
+        let bits = BitVec::from_words(vec![u64::MAX, u64::MAX], 70).unwrap();
+        assert_eq!(bits.count_ones(), 70);
+        assert_eq!(bits.words().len(), 2);
+        assert!(BitVec::from_words(vec![0], 65).is_err());
+    }
+}
@@ -1,7 +1,8 @@
 use std::fmt::Display;
-use fasthash::{murmur3, spooky};
+use fasthash::murmur3;
+use crate::bit_vec::BitVec;
 use crate::Error;
-use crate::set::Set;
+use crate::set::{ApproximateSet, ErrorBounds, Set};
 
 pub struct BloomFilter {
     // Max number of elements
@@ -13,87 +14,70 @@ pub struct BloomFilter {
     // Number of indices per element
     k: usize,
     // The filter
-    bytes: Box<[u8]>,
-    // Functions that compute the k'th index of an input
-    hash_fns: Vec<Box<dyn Fn(String) -> usize>>,
+    bits: BitVec,
+    // Set once a difference may have cleared bits belonging to remaining elements
+    false_negatives: bool,
 }
 
 impl BloomFilter {
     pub fn new(capacity: usize, p: f64) -> Self {
         // let ln2 = 2_f64.ln();
 
-        let m: usize = ((p.ln() * -1.0 * (capacity as f64)) / 2_f64.ln().powi(2)) as usize;
+        // At least one bit and one hash function, or a filter for no elements (or p >= 1) would
+        // report every key present
+        let m: usize = (((-p.ln() * (capacity as f64)) / 2_f64.ln().powi(2)) as usize).max(1);
 
-        let k = m / capacity;
-
-        let num_bytes = m.div_ceil(8);
-
-        let mut hash_fns = Vec::new();
-        for i in 0..k {
-            hash_fns.push(Self::gen_hash_fn(i as u32, m as u32));
-        }
+        // The k that minimizes false positives for m bits and n elements is m/n * ln 2
+        let k = if capacity == 0 { 1 } else { ((m as f64 / capacity as f64) * 2_f64.ln()).round().max(1.0) as usize };
 
         BloomFilter {
             capacity,
             p,
             num_bits: m,
             k,
-            bytes: vec![0x00; num_bytes].into_boxed_slice(),
-            hash_fns,
+            bits: BitVec::new(m),
+            false_negatives: false,
         }
     }
-    fn bit_coordinates(index: usize) -> (usize, usize) {
-        let byte_index = index / 8;
-        let bit_offset = index % 8;
-        (byte_index, bit_offset)
-    }
-    fn read_bit(&self, index: usize) -> u8 {
-        let (byte_index, bit_offset) = BloomFilter::bit_coordinates(index);
-        let bitmask: u8 = 1 << bit_offset;
-        (self.bytes[byte_index] & bitmask) >> bit_offset
-    }
-    fn write_bit(&mut self, index: usize) {
-        let (byte_index, bit_offset) = BloomFilter::bit_coordinates(index);
-        self.bytes[byte_index] |= 1 << bit_offset;
-    }
+    // Enhanced double hashing: the i'th index is h1 + i * h2 + (i^3 - i) / 6, with both halves of
+    // one 128-bit Murmur3 hash. Forcing h2 odd keeps the k indices from collapsing onto one when it
+    // shares factors with m, and the cubic term keeps two keys whose progressions meet once from
+    // running in step.
     fn key_to_bit_indicies(&self, key: String) -> Vec<usize> {
-        let mut indicies: Vec<usize> = Vec::new();
-        for i in 0..self.k {
-            indicies.push(self.hash_fns[i](key.clone()));
-        }
-        indicies
-    }
-    fn gen_hash_fn(i: u32, num_bits: u32) -> Box<dyn Fn(String) -> usize> {
-        let hash_fn: Box<dyn Fn(String) -> usize> = Box::new(move |s: String| {
-            let s = &s;
-            let murmur = murmur3::hash32(s);
-            let spooky: u32 = spooky::hash32(s);
-            // XOR of Murmur + i and Spooky + i squared, fit to the
-            let h: u32 = ((murmur + i) ^ (spooky + i * i)) % num_bits;
-            h as usize
-        });
-        hash_fn
+        let hash = murmur3::hash128(&key);
+        let h1 = hash as u64;
+        let h2 = (hash >> 64) as u64 | 1;
+        (0..self.k as u64)
+            .map(|i| (h1.wrapping_add(i.wrapping_mul(h2)).wrapping_add((i * i * i - i) / 6) % self.num_bits as u64) as usize)
+            .collect()
     }
     pub fn insert(&mut self, value: String) {
         let indicies = self.key_to_bit_indicies(value);
         for i in indicies {
-            self.write_bit(i);
+            self.bits.set(i, true);
         }
     }
     pub fn contains(&self, value: String) -> bool {
         let indicies = self.key_to_bit_indicies(value);
-        let mut set_bits = 0;
-        for i in indicies {
-            set_bits += self.read_bit(i);
-        }
-        set_bits as usize == self.k
+        indicies.into_iter().all(|i| self.bits.get(i))
     }
 
     pub fn can_compare(&self, rhs: &BloomFilter) -> bool {
         self.num_bits == rhs.num_bits && self.p == rhs.p && self.capacity == rhs.capacity && self.k == rhs.k
     }
     pub fn false_positive_probability(&self) -> f64 {
-        self.p.clone()
+        self.p
+    }
+    pub fn estimated_fpr(&self) -> f64 {
+        let fill = self.bits.count_ones() as f64 / self.num_bits as f64;
+        fill.powi(self.k as i32)
+    }
+    pub fn clear(&mut self) {
+        self.bits.fill(false);
+        self.false_negatives = false;
+    }
+    pub fn may_have_false_negatives(&self) -> bool {
+        self.false_negatives
     }
 }
 
@@ -106,50 +90,73 @@ impl Set for BloomFilter {
     fn contains(&self, e: String) -> bool {
         self.contains(e)
     }
+    fn error_bounds(&self) -> ErrorBounds {
+        ErrorBounds {
+            false_positive_rate: self.estimated_fpr(),
+            false_negatives: self.false_negatives,
+        }
+    }
 
     fn equals(&self, other: Self) -> bool {
         if !self.can_compare(&other) {
             return false;
         }
-        for i in 0..self.bytes.len() {
-            if self.bytes[i] ^ other.bytes[i] != 0 {
-                return false;
-            }
-        }
-        return true;
+        self.bits == other.bits
     }
 
+    // Every element of either input is still present; the false positive rate grows with the
+    // combined fill.
     fn union(&self, other: Self) -> crate::Result<Self> {
         if !self.can_compare(&other) {
             return Err(Error::IllegalArguments(String::from("Bloom Filters must have the same parameters.")));
         }
         let mut union = BloomFilter::new(self.capacity, self.p);
-        for i in 0..self.bytes.len() {
-            union.bytes[i] = self.bytes[i] | other.bytes[i];
-        }
-        return Ok(union);
+        union.bits = self.bits.clone();
+        union.bits.or(&other.bits)?;
+        union.false_negatives = self.false_negatives || other.false_negatives;
+        Ok(union)
     }
 
+    // Every common element is still present, but bits shared by distinct elements of each input
+    // survive too, so the false positive rate can exceed that of a filter built from the true
+    // intersection.
     fn intersection(&self, other: Self) -> crate::Result<Self> {
         if !self.can_compare(&other) {
             return Err(Error::IllegalArguments(String::from("Bloom Filters must have the same parameters.")));
         }
         let mut intersection = BloomFilter::new(self.capacity, self.p);
-        for i in 0..self.bytes.len() {
-            intersection.bytes[i] = self.bytes[i] & other.bytes[i];
-        }
-        return Ok(intersection);
+        intersection.bits = self.bits.clone();
+        intersection.bits.and(&other.bits)?;
+        intersection.false_negatives = self.false_negatives || other.false_negatives;
+        Ok(intersection)
     }
 
+    // Clearing the bits of `other` also clears bits that remaining elements share with it, so the
+    // result may report false negatives.
     fn difference(&self, other: Self) -> crate::Result<Self> {
         if !self.can_compare(&other)  {
             return Err(Error::IllegalArguments(String::from("Bloom Filters must have the same parameters.")));
         }
-        let mut intersection = BloomFilter::new(self.capacity, self.p);
-        for i in 0..self.bytes.len() {
-            intersection.bytes[i] = self.bytes[i] & (self.bytes[i] ^ other.bytes[i]);
-        }
-        return Ok(intersection);
+        let mut difference = BloomFilter::new(self.capacity, self.p);
+        difference.bits = self.bits.clone();
+        difference.bits.and_not(&other.bits)?;
+        difference.false_negatives = true;
+        Ok(difference)
+    }
+}
+
+impl ApproximateSet for BloomFilter {
+    fn false_positive_probability(&self) -> f64 {
+        self.false_positive_probability()
+    }
+    fn estimated_fpr(&self) -> f64 {
+        self.estimated_fpr()
+    }
+    fn slot_count(&self) -> usize {
+        self.num_bits
+    }
+    fn slots(&self, e: String) -> Vec<usize> {
+        self.key_to_bit_indicies(e)
     }
 }
 
@@ -168,7 +175,8 @@ impl Display for BloomFilter {
 
 #[cfg(test)]
 mod test {
-    use crate::set::Set;
+    use crate::set::conformance::{self, Config};
+    use crate::set::{ApproximateSet, Membership, Set};
     use super::BloomFilter;
 
     #[test]
@@ -318,4 +326,87 @@ mod test {
         assert!(!difference.contains(String::from("B")));
         assert!(!difference.contains(String::from("C")));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_clear() {
+        let mut bf: BloomFilter = BloomFilter::new(128, 0.01);
+        bf.insert(String::from("A"));
+        assert!(bf.contains(String::from("A")));
+
+        bf.clear();
+        assert!(!bf.contains(String::from("A")));
+        assert!(bf.equals(BloomFilter::new(128, 0.01)));
+    }
+
+    #[test]
+    fn test_estimated_fpr() {
+        let mut bf: BloomFilter = BloomFilter::new(128, 0.01);
+        assert_eq!(bf.estimated_fpr(), 0.0);
+        for i in 0..128 {
+            bf.insert(format!("key-{i}"));
+        }
+        let estimate = ApproximateSet::estimated_fpr(&bf);
+        assert!(estimate > 0.0 && estimate < 0.1, "estimate was {estimate}");
+        assert_eq!(ApproximateSet::false_positive_probability(&bf), 0.01);
+    }
+
+    #[test]
+    fn test_membership() {
+        let mut bf: BloomFilter = BloomFilter::new(128, 0.01);
+        bf.insert(String::from("A"));
+
+        assert!(matches!(bf.membership(String::from("A")), Membership::Probably { .. }));
+        assert_eq!(bf.membership(String::from("B")), Membership::DefinitelyNot);
+    }
+
+    #[test]
+    fn test_difference_reports_false_negatives() {
+        let mut bf1: BloomFilter = BloomFilter::new(128, 0.01);
+        let mut bf2: BloomFilter = BloomFilter::new(128, 0.01);
+        bf1.insert(String::from("A"));
+        bf1.insert(String::from("B"));
+        bf2.insert(String::from("B"));
+
+        let difference = bf1.difference(bf2).unwrap();
+        assert!(difference.may_have_false_negatives());
+        assert!(difference.error_bounds().false_negatives);
+        assert_eq!(difference.membership(String::from("B")), Membership::ProbablyNot);
+
+        let union = difference.union(BloomFilter::new(128, 0.01)).unwrap();
+        assert!(union.may_have_false_negatives());
+    }
+
+    #[test]
+    fn test_difference_loses_shared_bits() {
+        let mut bf1: BloomFilter = BloomFilter::new(128, 0.01);
+        let mut bf2: BloomFilter = BloomFilter::new(128, 0.01);
+        bf1.insert(String::from("A"));
+        // Some key sharing a bit with A, whose removal clears that bit too
+        let a_bits = bf1.key_to_bit_indicies(String::from("A"));
+        let shared = (0..)
+            .map(|i| format!("key-{i}"))
+            .find(|key| bf2.key_to_bit_indicies(key.clone()).iter().any(|i| a_bits.contains(i)))
+            .unwrap();
+        bf1.insert(shared.clone());
+        bf2.insert(shared);
+
+        let difference = bf1.difference(bf2).unwrap();
+        assert!(!difference.contains(String::from("A")));
+        assert_eq!(difference.membership(String::from("A")), Membership::ProbablyNot);
+    }
+
+    #[test]
+    fn test_empty_capacity_or_certain_fpr() {
+        for mut bf in [BloomFilter::new(0, 0.01), BloomFilter::new(10, 1.0)] {
+            assert!(!bf.contains(String::from("A")));
+            bf.insert(String::from("A"));
+            assert!(bf.contains(String::from("A")));
+        }
+    }
+
+    #[test]
+    fn test_conformance() {
+        let report = conformance::check_set(&Config::default(), || BloomFilter::new(1_000, 0.01), |id| format!("element-{id}"));
+        assert!(report.declared_fpr < 0.01);
+    }
+}
@@ -1,6 +1,6 @@
 use std::cmp::{Eq, PartialEq};
-use std::{collections::HashSet, fmt::Display};
-use std::fmt::{Debug, Formatter};
+use std::fmt::Display;
+use std::fmt::Debug;
 
 pub mod search;
 pub mod path;
@@ -47,7 +47,7 @@ impl<'a, T: Eq + Display, W> Edge<'a, T, W> where W: Default {
         Edge {
             subject: from,
             object: to,
-            weight: weight
+            weight
         }
     }
 }
@@ -1,6 +1,12 @@
+use std::collections::HashMap;
 use std::fmt::Display;
+use std::ops::Add;
 use crate::graph::{Edge, Graph, Node};
+use crate::queue::indexed::IndexedPriorityQueue;
+use crate::queue::AddressableHeap;
 
+// Follows directed edges and treats `W::default()` as a zero distance. Returns the edges of a
+// shortest path in travel order, or nothing if `to` is unreachable.
 pub fn dijkstra<'a, T, W>(
     g: &'a Graph<'a, T, W>,
     from: &'a Node<'a, T>,
@@ -8,17 +14,154 @@ pub fn dijkstra<'a, T, W>(
 ) -> Vec<&'a Edge<'a, T, W>>
     where
         T: Eq + Display,
+        W: Ord + Copy + Default + Add<Output = W>,
 {
-    let mut path: Vec<&Edge<T, W>> = Vec::new();
-    todo!()
+    let mut outgoing: HashMap<&str, Vec<&Edge<T, W>>> = HashMap::new();
+    for &edge in &g.edges {
+        outgoing.entry(edge.subject.id).or_default().push(edge);
+    }
+    let mut distances: HashMap<&str, W> = HashMap::new();
+    let mut via: HashMap<&str, &Edge<T, W>> = HashMap::new();
+    let mut frontier = IndexedPriorityQueue::new_min();
+    frontier.push(from.id, W::default());
+    while let Some((id, distance)) = frontier.pop() {
+        distances.insert(id, distance);
+        if id == to.id {
+            break;
+        }
+        for &edge in outgoing.get(id).into_iter().flatten() {
+            let next = edge.object.id;
+            if distances.contains_key(next) {
+                continue;
+            }
+            let candidate = distance + edge.weight;
+            let improved = if frontier.contains_key(&next) {
+                frontier.decrease_key(&next, candidate)
+            } else {
+                frontier.push(next, candidate);
+                true
+            };
+            if improved {
+                via.insert(next, edge);
+            }
+        }
+    }
+    trace_back(&distances, &via, from, to)
 }
 
-pub fn a_star<'a, T: Eq + Display, W>(
+/// Like `dijkstra`, but queues the frontier in any addressable heap that serves the smallest
+/// `(distance, id)` first, such as `PairingHeap::new_min()` or `FibonacciHeap::new_min()`.
+pub fn dijkstra_with<'a, T, W, H>(
     g: &'a Graph<'a, T, W>,
     from: &'a Node<'a, T>,
     to: &'a Node<'a, T>,
+    mut frontier: H,
+) -> Vec<&'a Edge<'a, T, W>>
+    where
+        T: Eq + Display,
+        W: Ord + Copy + Default + Add<Output = W>,
+        H: AddressableHeap<T = (W, &'a str)>,
+{
+    let mut outgoing: HashMap<&str, Vec<&Edge<T, W>>> = HashMap::new();
+    for &edge in &g.edges {
+        outgoing.entry(edge.subject.id).or_default().push(edge);
+    }
+    let mut distances: HashMap<&str, W> = HashMap::new();
+    let mut via: HashMap<&str, &Edge<T, W>> = HashMap::new();
+    let mut handles: HashMap<&str, H::Handle> = HashMap::new();
+    handles.insert(from.id, frontier.push_with_handle((W::default(), from.id)));
+    while let Some((distance, id)) = frontier.pop() {
+        distances.insert(id, distance);
+        if id == to.id {
+            break;
+        }
+        for &edge in outgoing.get(id).into_iter().flatten() {
+            let next = edge.object.id;
+            if distances.contains_key(next) {
+                continue;
+            }
+            let candidate = distance + edge.weight;
+            let improved = match handles.get(next) {
+                Some(handle) => matches!(frontier.decrease_key(handle, (candidate, next)), Ok((old, _)) if candidate < old),
+                None => {
+                    handles.insert(next, frontier.push_with_handle((candidate, next)));
+                    true
+                }
+            };
+            if improved {
+                via.insert(next, edge);
+            }
+        }
+    }
+    trace_back(&distances, &via, from, to)
+}
+
+// Follows the recorded edges back from `to`, if it was reached.
+fn trace_back<'a, T: Eq + Display, W>(
+    distances: &HashMap<&str, W>,
+    via: &HashMap<&str, &'a Edge<'a, T, W>>,
+    from: &Node<T>,
+    to: &Node<T>,
+) -> Vec<&'a Edge<'a, T, W>> {
+    if !distances.contains_key(to.id) {
+        return Vec::new();
+    }
+    let mut path = Vec::new();
+    let mut current = to.id;
+    while current != from.id {
+        let edge = via[current];
+        path.push(edge);
+        current = edge.subject.id;
+    }
+    path.reverse();
+    path
+}
+
+pub fn a_star<'a, T: Eq + Display, W>(
+    _g: &'a Graph<'a, T, W>,
+    _from: &'a Node<'a, T>,
+    _to: &'a Node<'a, T>,
 ) -> Vec<&'a Edge<'a, T, W>> {
 
 
     todo!()
-}
\ No newline at end of file
+}
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::queue::fibonacci::FibonacciHeap;
+    use crate::queue::pairing::PairingHeap;
+
+    #[test]
+    pub fn test_dijkstra() {
+        let a: Node<char> = Node::new("A", 'a');
+        let b: Node<char> = Node::new("B", 'b');
+        let c = Node::new("C", 'c');
+        let d = Node::new("D", 'd');
+        let e = Node::new("E", 'e');
+        let a_to_b = Edge::new_weighted(&a, &b, 4);
+        let a_to_c = Edge::new_weighted(&a, &c, 1);
+        let c_to_b = Edge::new_weighted(&c, &b, 2);
+        let b_to_d = Edge::new_weighted(&b, &d, 1);
+        let c_to_d = Edge::new_weighted(&c, &d, 5);
+        let d_to_a = Edge::new_weighted(&d, &a, 1);
+        let g: Graph<char, u32> = Graph {
+            nodes: vec![&a, &b, &c, &d, &e],
+            edges: vec![&a_to_b, &a_to_c, &c_to_b, &b_to_d, &c_to_d, &d_to_a],
+        };
+
+        let path = dijkstra(&g, &a, &d);
+        let hops: Vec<(&str, &str, u32)> = path.iter().map(|e| (e.subject.id, e.object.id, e.weight)).collect();
+        assert_eq!(hops, vec![("A", "C", 1), ("C", "B", 2), ("B", "D", 1)]);
+        assert!(dijkstra(&g, &a, &e).is_empty());
+        assert!(dijkstra(&g, &a, &a).is_empty());
+
+        let pairing = dijkstra_with(&g, &a, &d, PairingHeap::new_min());
+        let fibonacci = dijkstra_with(&g, &a, &d, FibonacciHeap::new_min());
+        for other in [pairing, fibonacci] {
+            assert_eq!(other.len(), path.len());
+            assert!(other.iter().zip(&path).all(|(x, y)| std::ptr::eq(*x, *y)));
+        }
+        assert!(dijkstra_with(&g, &a, &e, FibonacciHeap::new_min()).is_empty());
+    }
+}
@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 use std::fmt::Display;
-use crate::graph::{Edge, Graph, Node};
+use crate::graph::{Graph, Node};
 
 pub fn depth_first_search<'a, T: Eq + Display>(
     g: &'a Graph<'a, T, ()>,
@@ -39,8 +39,8 @@ pub fn depth_first_search<'a, T: Eq + Display>(
 
 // "Patient"
 pub fn breadth_first_search<'a, T: Eq + Display>(
-    g: &'a Graph<'a, T, ()>,
-    search: T,
+    _g: &'a Graph<'a, T, ()>,
+    _search: T,
 ) -> Option<&'a Node<'a, T>> {
     todo!()
 }
@@ -48,6 +48,7 @@ pub fn breadth_first_search<'a, T: Eq + Display>(
 #[cfg(test)]
 pub mod test {
     use super::*;
+    use crate::graph::Edge;
     #[test]
     pub fn test_depth_first_search() {
         let a: Node<char> = Node::new("A", 'a');
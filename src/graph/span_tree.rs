@@ -1,13 +1,115 @@
+use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 use crate::graph::{Edge, Graph};
+use crate::queue::indexed::IndexedPriorityQueue;
+use crate::set::disjoint_set::KeyedDisjointSet;
 
+// Treats every edge as undirected and grows one tree per component, so a disconnected graph
+// yields a minimum spanning forest.
 pub fn prims_mst<'a, T, W>(g: &'a Graph<'a, T, W>) -> Vec<&'a Edge<'a, T, W>>
     where
         T: Eq + Display,
+        W: Ord,
 {
-    todo!()
+    let mut incident: HashMap<&str, Vec<&Edge<T, W>>> = HashMap::new();
+    for &edge in &g.edges {
+        incident.entry(edge.subject.id).or_default().push(edge);
+        incident.entry(edge.object.id).or_default().push(edge);
+    }
+    let mut in_tree: HashSet<&str> = HashSet::new();
+    // The cheapest known edge joining each frontier node to the tree
+    let mut cheapest: HashMap<&str, &Edge<T, W>> = HashMap::new();
+    let mut frontier: IndexedPriorityQueue<&str, Option<&W>, _> = IndexedPriorityQueue::new_min();
+    let mut mst = Vec::new();
+    for root in &g.nodes {
+        if in_tree.contains(root.id) {
+            continue;
+        }
+        // A root joins its tree without an edge, so it sorts before every weight
+        frontier.push(root.id, None);
+        while let Some((id, _)) = frontier.pop() {
+            in_tree.insert(id);
+            if let Some(edge) = cheapest.remove(id) {
+                mst.push(edge);
+            }
+            for &edge in incident.get(id).into_iter().flatten() {
+                let other = if edge.subject.id == id { edge.object.id } else { edge.subject.id };
+                if in_tree.contains(other) {
+                    continue;
+                }
+                let improved = if frontier.contains_key(&other) {
+                    frontier.decrease_key(&other, Some(&edge.weight))
+                } else {
+                    frontier.push(other, Some(&edge.weight));
+                    true
+                };
+                if improved {
+                    cheapest.insert(other, edge);
+                }
+            }
+        }
+    }
+    mst
 }
 
-pub fn kruskals_mst<'a, T: Eq + Display, W>(g: &'a Graph<'a, T, W>) -> Vec<&'a Edge<'a, T, W>> {
-    todo!()
-}
\ No newline at end of file
+// Treats every edge as undirected; a disconnected graph yields a minimum spanning forest.
+pub fn kruskals_mst<'a, T: Eq + Display, W: Ord>(g: &'a Graph<'a, T, W>) -> Vec<&'a Edge<'a, T, W>> {
+    let mut components: KeyedDisjointSet<&str> = g.nodes.iter().map(|n| n.id).collect();
+    let mut edges: Vec<&Edge<T, W>> = g.edges.clone();
+    edges.sort_by(|a, b| a.weight.cmp(&b.weight));
+    edges
+        .into_iter()
+        .filter(|e| components.union(e.subject.id, e.object.id))
+        .collect()
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::graph::Node;
+
+    #[test]
+    pub fn test_kruskals_mst() {
+        let a: Node<char> = Node::new("A", 'a');
+        let b: Node<char> = Node::new("B", 'b');
+        let c = Node::new("C", 'c');
+        let d = Node::new("D", 'd');
+        let a_to_b = Edge::new_weighted(&a, &b, 1);
+        let b_to_c = Edge::new_weighted(&b, &c, 4);
+        let a_to_c = Edge::new_weighted(&a, &c, 3);
+        let c_to_d = Edge::new_weighted(&c, &d, 2);
+        let b_to_d = Edge::new_weighted(&b, &d, 5);
+        let g: Graph<char, u32> = Graph {
+            nodes: vec![&a, &b, &c, &d],
+            edges: vec![&a_to_b, &b_to_c, &a_to_c, &c_to_d, &b_to_d],
+        };
+        let mst = kruskals_mst(&g);
+        let weights: Vec<u32> = mst.iter().map(|e| e.weight).collect();
+        assert_eq!(weights, vec![1, 2, 3]);
+    }
+
+    #[test]
+    pub fn test_prims_mst() {
+        let a: Node<char> = Node::new("A", 'a');
+        let b: Node<char> = Node::new("B", 'b');
+        let c = Node::new("C", 'c');
+        let d = Node::new("D", 'd');
+        let e = Node::new("E", 'e');
+        let f = Node::new("F", 'f');
+        let a_to_b = Edge::new_weighted(&a, &b, 1);
+        let b_to_c = Edge::new_weighted(&b, &c, 4);
+        let a_to_c = Edge::new_weighted(&a, &c, 3);
+        let c_to_d = Edge::new_weighted(&c, &d, 2);
+        let b_to_d = Edge::new_weighted(&b, &d, 5);
+        let e_to_f = Edge::new_weighted(&e, &f, 7);
+        let g: Graph<char, u32> = Graph {
+            nodes: vec![&a, &b, &c, &d, &e, &f],
+            edges: vec![&a_to_b, &b_to_c, &a_to_c, &c_to_d, &b_to_d, &e_to_f],
+        };
+        let mut weights: Vec<u32> = prims_mst(&g).iter().map(|e| e.weight).collect();
+        weights.sort();
+        assert_eq!(weights, vec![1, 2, 3, 7]);
+        let total = |edges: Vec<&Edge<char, u32>>| edges.iter().map(|e| e.weight).sum::<u32>();
+        assert_eq!(total(prims_mst(&g)), total(kruskals_mst(&g)));
+    }
+}
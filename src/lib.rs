@@ -2,8 +2,10 @@ use std::fmt::Formatter;
 
 pub mod set;
 pub mod bloom_filter;
+pub mod bit_vec;
 pub mod queue;
 pub mod graph;
+pub mod map;
 
 #[derive(Debug)]
 pub enum Error {
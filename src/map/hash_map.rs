@@ -0,0 +1,412 @@
+use std::collections::hash_map::RandomState;
+use std::fmt::{Debug, Formatter};
+use std::hash::{BuildHasher, Hash};
+use std::mem;
+use crate::Error;
+use crate::map::Map;
+use crate::set::hash_set::HashSet;
+
+const DEFAULT_LOAD_FACTOR: f64 = 0.875;
+const MIN_SLOTS: usize = 8;
+
+struct Slot<K, V> {
+    hash: u64,
+    key: K,
+    value: V,
+}
+
+/// A hash map using Robin Hood open addressing.
+///
+/// Each entry sits as close to its home slot as possible, with entries far from home taking
+/// precedence over entries near it. Removal shifts the following run back by one slot instead of
+/// leaving a tombstone, so lookups never probe past a stale entry.
+pub struct HashMap<K: Hash + Eq, V> {
+    // Always empty or a power of two in length
+    slots: Vec<Option<Slot<K, V>>>,
+    len: usize,
+    // Fraction of slots that may be occupied before the table grows
+    load_factor: f64,
+    hasher: RandomState,
+}
+
+impl<K: Hash + Eq, V> HashMap<K, V> {
+    pub fn new() -> Self {
+        HashMap {
+            slots: Vec::new(),
+            len: 0,
+            load_factor: DEFAULT_LOAD_FACTOR,
+            hasher: RandomState::new(),
+        }
+    }
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut map = Self::new();
+        map.reserve(capacity);
+        map
+    }
+    pub fn with_load_factor(capacity: usize, load_factor: f64) -> crate::Result<Self> {
+        if !(load_factor > 0.0 && load_factor < 1.0) {
+            return Err(Error::IllegalArguments(format!("Load factor must be in (0, 1), got {load_factor}.")));
+        }
+        let mut map = Self::new();
+        map.load_factor = load_factor;
+        map.reserve(capacity);
+        Ok(map)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// Number of entries the map can hold without growing.
+    pub fn capacity(&self) -> usize {
+        (self.slots.len() as f64 * self.load_factor) as usize
+    }
+    pub fn load_factor(&self) -> f64 {
+        self.load_factor
+    }
+
+    /// Makes room for at least `additional` more entries.
+    pub fn reserve(&mut self, additional: usize) {
+        let required = self.len + additional;
+        if required <= self.capacity() {
+            return;
+        }
+        let mut num_slots = self.slots.len().max(MIN_SLOTS);
+        while ((num_slots as f64 * self.load_factor) as usize) < required {
+            num_slots *= 2;
+        }
+        self.resize(num_slots);
+    }
+    pub fn shrink_to_fit(&mut self) {
+        let mut num_slots = MIN_SLOTS;
+        while ((num_slots as f64 * self.load_factor) as usize) < self.len {
+            num_slots *= 2;
+        }
+        if num_slots < self.slots.len() {
+            self.resize(num_slots);
+        }
+    }
+
+    /// Maps `key` to `value`, returning the value it replaced. An existing key is kept as is.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.find(&key) {
+            Some(index) => self.slots[index].as_mut().map(|slot| mem::replace(&mut slot.value, value)),
+            None => {
+                self.insert_new(key, value);
+                None
+            }
+        }
+    }
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.get_key_value(key).map(|(_, value)| value)
+    }
+    pub fn get_key_value(&self, key: &K) -> Option<(&K, &V)> {
+        self.find(key).and_then(|i| self.slots[i].as_ref()).map(|slot| (&slot.key, &slot.value))
+    }
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.find(key).and_then(|i| self.slots[i].as_mut()).map(|slot| &mut slot.value)
+    }
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.find(key).is_some()
+    }
+    /// The value for `key`, first inserting the result of `default` if there is none.
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, default: F) -> &mut V {
+        let index = match self.find(&key) {
+            Some(index) => index,
+            None => self.insert_new(key, default()),
+        };
+        &mut self.slots[index].as_mut().expect("Slot was just found or filled").value
+    }
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+    pub fn remove_entry(&mut self, key: &K) -> Option<(K, V)> {
+        let index = self.find(key)?;
+        let removed = self.slots[index].take().map(|slot| (slot.key, slot.value));
+        self.len -= 1;
+
+        // Backward shift: pull the rest of the probe run one slot closer to home
+        let mask = self.mask();
+        let mut hole = index;
+        let mut next = (index + 1) & mask;
+        while let Some(slot) = &self.slots[next] {
+            if Self::probe_distance(slot.hash, next, mask) == 0 {
+                break;
+            }
+            self.slots[hole] = self.slots[next].take();
+            hole = next;
+            next = (next + 1) & mask;
+        }
+        removed
+    }
+    pub fn clear(&mut self) {
+        self.slots.iter_mut().for_each(|slot| *slot = None);
+        self.len = 0;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        self.slots.iter().filter_map(|slot| slot.as_ref().map(|slot| (&slot.key, &slot.value)))
+    }
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut V)> + '_ {
+        self.slots.iter_mut().filter_map(|slot| slot.as_mut().map(|slot| (&slot.key, &mut slot.value)))
+    }
+    pub fn keys(&self) -> impl Iterator<Item = &K> + '_ {
+        self.iter().map(|(key, _)| key)
+    }
+    pub fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.iter().map(|(_, value)| value)
+    }
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> + '_ {
+        self.iter_mut().map(|(_, value)| value)
+    }
+
+    /// The keys as a `HashSet`, reusing the table layout instead of rehashing.
+    pub fn into_key_set(self) -> HashSet<K> {
+        HashSet::from(self.map_values(|_| ()))
+    }
+    pub fn key_set(&self) -> HashSet<K> where K: Clone {
+        HashSet::from(HashMap {
+            slots: self.slots.iter()
+                .map(|slot| slot.as_ref().map(|slot| Slot { hash: slot.hash, key: slot.key.clone(), value: () }))
+                .collect(),
+            len: self.len,
+            load_factor: self.load_factor,
+            hasher: self.hasher.clone(),
+        })
+    }
+    /// Transforms every value in place, keeping the table layout.
+    pub fn map_values<U, F: FnMut(V) -> U>(self, mut f: F) -> HashMap<K, U> {
+        HashMap {
+            slots: self.slots.into_iter()
+                .map(|slot| slot.map(|slot| Slot { hash: slot.hash, key: slot.key, value: f(slot.value) }))
+                .collect(),
+            len: self.len,
+            load_factor: self.load_factor,
+            hasher: self.hasher,
+        }
+    }
+
+    fn hash(&self, key: &K) -> u64 {
+        self.hasher.hash_one(key)
+    }
+    fn mask(&self) -> usize {
+        self.slots.len() - 1
+    }
+    fn probe_distance(hash: u64, index: usize, mask: usize) -> usize {
+        index.wrapping_sub(hash as usize) & mask
+    }
+    fn find(&self, key: &K) -> Option<usize> {
+        if self.len == 0 {
+            return None;
+        }
+        let mask = self.mask();
+        let hash = self.hash(key);
+        let mut index = hash as usize & mask;
+        let mut distance = 0;
+        while let Some(slot) = &self.slots[index] {
+            // A resident closer to home than we are means the key would have displaced it
+            if Self::probe_distance(slot.hash, index, mask) < distance {
+                return None;
+            }
+            if slot.hash == hash && slot.key == *key {
+                return Some(index);
+            }
+            index = (index + 1) & mask;
+            distance += 1;
+        }
+        None
+    }
+    // Adds a key known to be absent, returning the slot it landed in.
+    fn insert_new(&mut self, key: K, value: V) -> usize {
+        self.reserve(1);
+        let hash = self.hash(&key);
+        self.len += 1;
+        self.insert_unique(Slot { hash, key, value })
+    }
+    // Places a slot known to be absent, robbing richer residents along the way. Returns where the
+    // incoming slot itself ended up.
+    fn insert_unique(&mut self, mut incoming: Slot<K, V>) -> usize {
+        let mask = self.mask();
+        let mut index = incoming.hash as usize & mask;
+        let mut distance = 0;
+        let mut placed = None;
+        loop {
+            match &mut self.slots[index] {
+                None => {
+                    self.slots[index] = Some(incoming);
+                    return placed.unwrap_or(index);
+                }
+                Some(resident) => {
+                    let resident_distance = Self::probe_distance(resident.hash, index, mask);
+                    if resident_distance < distance {
+                        mem::swap(resident, &mut incoming);
+                        distance = resident_distance;
+                        placed.get_or_insert(index);
+                    }
+                }
+            }
+            index = (index + 1) & mask;
+            distance += 1;
+        }
+    }
+    fn resize(&mut self, num_slots: usize) {
+        let old_slots = mem::replace(&mut self.slots, (0..num_slots).map(|_| None).collect());
+        for slot in old_slots.into_iter().flatten() {
+            self.insert_unique(slot);
+        }
+    }
+}
+
+impl<K: Hash + Eq, V> Default for HashMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Clone for HashMap<K, V> {
+    fn clone(&self) -> Self {
+        HashMap {
+            slots: self.slots.iter()
+                .map(|slot| slot.as_ref().map(|slot| Slot { hash: slot.hash, key: slot.key.clone(), value: slot.value.clone() }))
+                .collect(),
+            len: self.len,
+            load_factor: self.load_factor,
+            hasher: self.hasher.clone(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Debug, V: Debug> Debug for HashMap<K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Hash + Eq, V> FromIterator<(K, V)> for HashMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = HashMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: Hash + Eq, V> Extend<(K, V)> for HashMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K: Hash + Eq, V> Map for HashMap<K, V> {
+    type K = K;
+    type V = V;
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.get(key)
+    }
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.get_mut(key)
+    }
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert(key, value)
+    }
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove(key)
+    }
+    fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, default: F) -> &mut V {
+        self.get_or_insert_with(key, default)
+    }
+    fn len(&self) -> usize {
+        self.len
+    }
+    fn iter(&self) -> Box<dyn Iterator<Item = (&K, &V)> + '_> {
+        Box::new(self.iter())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use crate::map::Map;
+    use crate::set::EnumerableSet;
+    use super::HashMap;
+
+    #[test]
+    fn test_insert_get_remove() {
+        let mut map: HashMap<String, u32> = HashMap::new();
+        assert_eq!(map.insert(String::from("a"), 1), None);
+        assert_eq!(map.insert(String::from("a"), 2), Some(1));
+        assert_eq!(map.insert(String::from("b"), 3), None);
+        assert_eq!(map.len(), 2);
+
+        assert_eq!(map.get(&String::from("a")), Some(&2));
+        *map.get_mut(&String::from("b")).unwrap() += 1;
+        assert_eq!(map.remove(&String::from("b")), Some(4));
+        assert_eq!(map.remove(&String::from("b")), None);
+        assert!(!map.contains_key(&String::from("b")));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn test_random_operations_match_std() {
+        let mut rng = StdRng::seed_from_u64(34);
+        let mut map: HashMap<u32, u32> = HashMap::new();
+        let mut expected: std::collections::HashMap<u32, u32> = std::collections::HashMap::new();
+        for step in 0..20_000 {
+            let key = rng.gen_range(0..2_000);
+            if rng.gen_bool(0.6) {
+                assert_eq!(map.insert(key, step), expected.insert(key, step));
+            } else {
+                assert_eq!(map.remove(&key), expected.remove(&key));
+            }
+            assert_eq!(map.len(), expected.len());
+        }
+        for key in 0..2_000 {
+            assert_eq!(map.get(&key), expected.get(&key));
+        }
+        assert_eq!(map.iter().count(), expected.len());
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut counts: HashMap<char, u32> = HashMap::new();
+        for c in "abracadabra".chars() {
+            *counts.entry(c).or_insert(0) += 1;
+        }
+        assert_eq!(counts.get(&'a'), Some(&5));
+        assert_eq!(counts.get(&'r'), Some(&2));
+
+        counts.entry('a').and_modify(|n| *n = 0).or_default();
+        counts.entry('z').and_modify(|n| *n = 100).or_default();
+        assert_eq!(counts.get(&'a'), Some(&0));
+        assert_eq!(counts.get(&'z'), Some(&0));
+        assert!(counts.entry('q').or_insert_with(|| 7) == &mut 7);
+        assert!(counts.entry('q').is_occupied());
+    }
+
+    #[test]
+    fn test_get_or_insert_with_after_displacement() {
+        let mut map: HashMap<u32, u32> = HashMap::with_capacity(64);
+        for key in 0..50 {
+            *map.get_or_insert_with(key, || 0) += key;
+        }
+        assert!((0..50).all(|key| map.get(&key) == Some(&key)));
+    }
+
+    #[test]
+    fn test_key_set() {
+        let map: HashMap<u32, &str> = [(1, "one"), (2, "two")].into_iter().collect();
+        let keys = map.key_set();
+        assert_eq!(EnumerableSet::len(&keys), 2);
+        assert!(keys.contains(&1) && keys.contains(&2));
+
+        let keys = map.into_key_set();
+        assert!(keys.contains(&1) && keys.contains(&2) && !keys.contains(&3));
+    }
+}
@@ -0,0 +1,425 @@
+use std::cmp::Ordering;
+use std::fmt::{Debug, Formatter};
+use std::ops::{Bound, Range};
+use crate::map::Map;
+
+type Link<K, V> = Option<Box<Node<K, V>>>;
+
+struct Node<K, V> {
+    range: Range<K>,
+    value: V,
+    // Largest `end` in this subtree, which lets queries skip subtrees that end too early
+    max_end: K,
+    height: u8,
+    left: Link<K, V>,
+    right: Link<K, V>,
+}
+
+impl<K: Ord + Clone, V> Node<K, V> {
+    fn leaf(range: Range<K>, value: V) -> Box<Self> {
+        Box::new(Node { max_end: range.end.clone(), range, value, height: 1, left: None, right: None })
+    }
+
+    fn update(&mut self) {
+        self.height = 1 + height(&self.left).max(height(&self.right));
+        let mut max_end = &self.range.end;
+        for child in [&self.left, &self.right].into_iter().flatten() {
+            max_end = max_end.max(&child.max_end);
+        }
+        self.max_end = max_end.clone();
+    }
+}
+
+fn height<K, V>(link: &Link<K, V>) -> u8 {
+    link.as_ref().map_or(0, |node| node.height)
+}
+
+// Orders ranges by start, then by end.
+fn compare<K: Ord>(a: &Range<K>, b: &Range<K>) -> Ordering {
+    a.start.cmp(&b.start).then_with(|| a.end.cmp(&b.end))
+}
+
+fn rotate_left<K: Ord + Clone, V>(mut node: Box<Node<K, V>>) -> Box<Node<K, V>> {
+    let mut pivot = node.right.take().expect("Rotated toward a missing child");
+    node.right = pivot.left.take();
+    node.update();
+    pivot.left = Some(node);
+    pivot.update();
+    pivot
+}
+
+fn rotate_right<K: Ord + Clone, V>(mut node: Box<Node<K, V>>) -> Box<Node<K, V>> {
+    let mut pivot = node.left.take().expect("Rotated toward a missing child");
+    node.left = pivot.right.take();
+    node.update();
+    pivot.right = Some(node);
+    pivot.update();
+    pivot
+}
+
+// Restores the AVL invariant at `node`, assuming both subtrees already satisfy it.
+fn rebalance<K: Ord + Clone, V>(mut node: Box<Node<K, V>>) -> Box<Node<K, V>> {
+    node.update();
+    let balance = height(&node.left) as i16 - height(&node.right) as i16;
+    if balance > 1 {
+        let left = node.left.take().expect("Left-heavy node has a left child");
+        node.left = Some(if height(&left.left) < height(&left.right) { rotate_left(left) } else { left });
+        return rotate_right(node);
+    }
+    if balance < -1 {
+        let right = node.right.take().expect("Right-heavy node has a right child");
+        node.right = Some(if height(&right.right) < height(&right.left) { rotate_right(right) } else { right });
+        return rotate_left(node);
+    }
+    node
+}
+
+fn insert<K: Ord + Clone, V>(link: Link<K, V>, range: Range<K>, value: V, replaced: &mut Option<V>) -> Box<Node<K, V>> {
+    let Some(mut node) = link else {
+        return Node::leaf(range, value);
+    };
+    match compare(&range, &node.range) {
+        Ordering::Less => node.left = Some(insert(node.left.take(), range, value, replaced)),
+        Ordering::Greater => node.right = Some(insert(node.right.take(), range, value, replaced)),
+        Ordering::Equal => {
+            *replaced = Some(std::mem::replace(&mut node.value, value));
+            return node;
+        }
+    }
+    rebalance(node)
+}
+
+fn remove<K: Ord + Clone, V>(link: Link<K, V>, range: &Range<K>, removed: &mut Option<(Range<K>, V)>) -> Link<K, V> {
+    let mut node = link?;
+    match compare(range, &node.range) {
+        Ordering::Less => node.left = remove(node.left.take(), range, removed),
+        Ordering::Greater => node.right = remove(node.right.take(), range, removed),
+        Ordering::Equal => {
+            let Node { range, value, left, right, .. } = *node;
+            *removed = Some((range, value));
+            let Some(right) = right else {
+                return left;
+            };
+            let (rest, mut successor) = remove_min(right);
+            successor.left = left;
+            successor.right = rest;
+            return Some(rebalance(successor));
+        }
+    }
+    Some(rebalance(node))
+}
+
+// Detaches the leftmost node, returning what remains of the subtree along with it.
+fn remove_min<K: Ord + Clone, V>(mut node: Box<Node<K, V>>) -> (Link<K, V>, Box<Node<K, V>>) {
+    match node.left.take() {
+        None => (node.right.take(), node),
+        Some(left) => {
+            let (rest, min) = remove_min(left);
+            node.left = rest;
+            (Some(rebalance(node)), min)
+        }
+    }
+}
+
+/// A map from ranges to values that finds every stored range overlapping a point or a range.
+///
+/// Ranges are half-open like `std::ops::Range` and kept in an AVL tree ordered by start. Each
+/// node also records the largest end below it, so a query only descends into subtrees that can
+/// reach it: O(log n + m) for m matches. Equal ranges share one entry; empty ranges may be
+/// stored but overlap nothing.
+pub struct IntervalTree<K: Ord, V> {
+    root: Link<K, V>,
+    len: usize,
+}
+
+impl<K: Ord + Clone, V> IntervalTree<K, V> {
+    pub fn new() -> Self {
+        IntervalTree { root: None, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Maps `range` to `value`, returning the value it replaced.
+    pub fn insert(&mut self, range: Range<K>, value: V) -> Option<V> {
+        let mut replaced = None;
+        self.root = Some(insert(self.root.take(), range, value, &mut replaced));
+        if replaced.is_none() {
+            self.len += 1;
+        }
+        replaced
+    }
+    pub fn get(&self, range: &Range<K>) -> Option<&V> {
+        let mut link = &self.root;
+        while let Some(node) = link {
+            link = match compare(range, &node.range) {
+                Ordering::Less => &node.left,
+                Ordering::Greater => &node.right,
+                Ordering::Equal => return Some(&node.value),
+            };
+        }
+        None
+    }
+    pub fn get_mut(&mut self, range: &Range<K>) -> Option<&mut V> {
+        let mut link = &mut self.root;
+        while let Some(node) = link {
+            link = match compare(range, &node.range) {
+                Ordering::Less => &mut node.left,
+                Ordering::Greater => &mut node.right,
+                Ordering::Equal => return Some(&mut node.value),
+            };
+        }
+        None
+    }
+    pub fn contains_key(&self, range: &Range<K>) -> bool {
+        self.get(range).is_some()
+    }
+    pub fn remove(&mut self, range: &Range<K>) -> Option<V> {
+        self.remove_entry(range).map(|(_, value)| value)
+    }
+    pub fn remove_entry(&mut self, range: &Range<K>) -> Option<(Range<K>, V)> {
+        let mut removed = None;
+        self.root = remove(self.root.take(), range, &mut removed);
+        if removed.is_some() {
+            self.len -= 1;
+        }
+        removed
+    }
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.len = 0;
+    }
+
+    /// All entries ordered by start, then end.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter::new(&self.root, Bound::Unbounded, Bound::Unbounded, false)
+    }
+    /// The entries whose range contains `point`, ordered by start.
+    pub fn stab(&self, point: &K) -> Iter<'_, K, V> {
+        Iter::new(&self.root, Bound::Excluded(point.clone()), Bound::Included(point.clone()), true)
+    }
+    /// The entries whose range shares at least one point with `range`, ordered by start.
+    pub fn overlapping(&self, range: &Range<K>) -> Iter<'_, K, V> {
+        if range.start >= range.end {
+            return Iter::new(&None, Bound::Unbounded, Bound::Unbounded, true);
+        }
+        Iter::new(&self.root, Bound::Excluded(range.start.clone()), Bound::Excluded(range.end.clone()), true)
+    }
+    /// Like `overlapping`, but also yields ranges that merely touch `range` at an end.
+    pub(crate) fn touching(&self, range: &Range<K>) -> Iter<'_, K, V> {
+        Iter::new(&self.root, Bound::Included(range.start.clone()), Bound::Included(range.end.clone()), true)
+    }
+}
+
+/// An in-order walk over the entries whose end passes `min_end` and whose start passes
+/// `max_start`, skipping subtrees that cannot hold any.
+pub struct Iter<'a, K, V> {
+    stack: Vec<&'a Node<K, V>>,
+    min_end: Bound<K>,
+    max_start: Bound<K>,
+    // Empty ranges hold no points, so queries leave them out
+    skip_empty: bool,
+}
+
+impl<'a, K: Ord, V> Iter<'a, K, V> {
+    fn new(root: &'a Link<K, V>, min_end: Bound<K>, max_start: Bound<K>, skip_empty: bool) -> Self {
+        let mut iter = Iter { stack: Vec::new(), min_end, max_start, skip_empty };
+        iter.descend(root);
+        iter
+    }
+
+    fn ends_after(&self, end: &K) -> bool {
+        match &self.min_end {
+            Bound::Included(min) => end >= min,
+            Bound::Excluded(min) => end > min,
+            Bound::Unbounded => true,
+        }
+    }
+    fn starts_before(&self, start: &K) -> bool {
+        match &self.max_start {
+            Bound::Included(max) => start <= max,
+            Bound::Excluded(max) => start < max,
+            Bound::Unbounded => true,
+        }
+    }
+
+    fn descend(&mut self, mut link: &'a Link<K, V>) {
+        while let Some(node) = link {
+            if !self.ends_after(&node.max_end) {
+                break;
+            }
+            self.stack.push(node);
+            link = &node.left;
+        }
+    }
+}
+
+impl<'a, K: Ord, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a Range<K>, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node) = self.stack.pop() {
+            if !self.starts_before(&node.range.start) {
+                // Everything left on the stack starts even later
+                self.stack.clear();
+                return None;
+            }
+            self.descend(&node.right);
+            if self.ends_after(&node.range.end) && !(self.skip_empty && node.range.is_empty()) {
+                return Some((&node.range, &node.value));
+            }
+        }
+        None
+    }
+}
+
+impl<K: Ord + Clone, V> Default for IntervalTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Clone for IntervalTree<K, V> {
+    fn clone(&self) -> Self {
+        self.iter().map(|(range, value)| (range.clone(), value.clone())).collect()
+    }
+}
+
+impl<K: Ord + Clone + Debug, V: Debug> Debug for IntervalTree<K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Ord + Clone, V> FromIterator<(Range<K>, V)> for IntervalTree<K, V> {
+    fn from_iter<I: IntoIterator<Item = (Range<K>, V)>>(iter: I) -> Self {
+        let mut tree = IntervalTree::new();
+        tree.extend(iter);
+        tree
+    }
+}
+
+impl<K: Ord + Clone, V> Extend<(Range<K>, V)> for IntervalTree<K, V> {
+    fn extend<I: IntoIterator<Item = (Range<K>, V)>>(&mut self, iter: I) {
+        for (range, value) in iter {
+            self.insert(range, value);
+        }
+    }
+}
+
+impl<K: Ord + Clone, V> Map for IntervalTree<K, V> {
+    type K = Range<K>;
+    type V = V;
+
+    fn get(&self, key: &Range<K>) -> Option<&V> {
+        self.get(key)
+    }
+    fn get_mut(&mut self, key: &Range<K>) -> Option<&mut V> {
+        self.get_mut(key)
+    }
+    fn insert(&mut self, key: Range<K>, value: V) -> Option<V> {
+        self.insert(key, value)
+    }
+    fn remove(&mut self, key: &Range<K>) -> Option<V> {
+        self.remove(key)
+    }
+    fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: Range<K>, default: F) -> &mut V {
+        if !self.contains_key(&key) {
+            self.insert(key.clone(), default());
+        }
+        self.get_mut(&key).expect("Inserted above")
+    }
+    fn len(&self) -> usize {
+        self.len
+    }
+    fn iter(&self) -> Box<dyn Iterator<Item = (&Range<K>, &V)> + '_> {
+        Box::new(self.iter())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ops::Range;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use super::{height, IntervalTree, Link};
+
+    // Checks the AVL balance and the `max_end` augmentation, returning the subtree height.
+    fn check<V>(link: &Link<u32, V>) -> u8 {
+        let Some(node) = link else {
+            return 0;
+        };
+        let (left, right) = (check(&node.left), check(&node.right));
+        assert!(left.abs_diff(right) <= 1);
+        assert_eq!(node.height, 1 + left.max(right));
+        let children = [&node.left, &node.right].into_iter().flatten().map(|child| child.max_end);
+        assert_eq!(node.max_end, children.fold(node.range.end, u32::max));
+        height(link)
+    }
+
+    #[test]
+    fn test_stab_and_overlap() {
+        let tree: IntervalTree<u32, &str> =
+            [(0..10, "a"), (5..15, "b"), (20..30, "c"), (12..13, "d"), (8..8, "empty")].into_iter().collect();
+        let names = |iter: super::Iter<'_, u32, &'static str>| iter.map(|(_, name)| *name).collect::<Vec<_>>();
+
+        assert_eq!(names(tree.stab(&7)), vec!["a", "b"]);
+        assert_eq!(names(tree.stab(&10)), vec!["b"]);
+        assert_eq!(names(tree.stab(&17)), Vec::<&str>::new());
+        assert_eq!(names(tree.overlapping(&(9..21))), vec!["a", "b", "d", "c"]);
+        assert_eq!(names(tree.overlapping(&(15..20))), Vec::<&str>::new());
+        assert_eq!(names(tree.overlapping(&(3..3))), Vec::<&str>::new());
+        assert_eq!(tree.get(&(8..8)), Some(&"empty"));
+    }
+
+    #[test]
+    fn test_random_operations_match_brute_force() {
+        let mut rng = StdRng::seed_from_u64(37);
+        let mut tree = IntervalTree::new();
+        let mut expected: Vec<(Range<u32>, u32)> = Vec::new();
+        for step in 0..3_000 {
+            let start = rng.gen_range(0..200);
+            let range = start..start + rng.gen_range(1..30);
+            if rng.gen_bool(0.6) {
+                let previous = expected.iter().position(|(r, _)| *r == range).map(|i| expected.remove(i).1);
+                assert_eq!(tree.insert(range.clone(), step), previous);
+                expected.push((range, step));
+            } else {
+                let previous = expected.iter().position(|(r, _)| *r == range).map(|i| expected.remove(i).1);
+                assert_eq!(tree.remove(&range), previous);
+            }
+        }
+        check(&tree.root);
+        expected.sort_by_key(|(range, _)| (range.start, range.end));
+        assert_eq!(tree.len(), expected.len());
+        assert!(tree.iter().eq(expected.iter().map(|(range, value)| (range, value))));
+
+        for _ in 0..200 {
+            let start = rng.gen_range(0..230);
+            let query = start..start + rng.gen_range(1..20);
+            let overlapping = expected.iter()
+                .filter(|(range, _)| range.start < query.end && query.start < range.end)
+                .map(|(range, value)| (range, value));
+            assert!(tree.overlapping(&query).eq(overlapping));
+            let stabbed = expected.iter()
+                .filter(|(range, _)| range.contains(&query.start))
+                .map(|(range, value)| (range, value));
+            assert!(tree.stab(&query.start).eq(stabbed));
+        }
+    }
+
+    #[test]
+    fn test_remove_keeps_balance() {
+        let mut tree: IntervalTree<u32, u32> = (0..500).map(|i| (i..i + 10, i)).collect();
+        for i in (0..500).step_by(3) {
+            assert_eq!(tree.remove(&(i..i + 10)), Some(i));
+        }
+        check(&tree.root);
+        assert_eq!(tree.len(), 333);
+        assert_eq!(tree.stab(&5).map(|(_, &v)| v).collect::<Vec<_>>(), vec![1, 2, 4, 5]);
+    }
+}
@@ -0,0 +1,59 @@
+pub mod hash_map;
+pub mod interval_tree;
+pub mod persistent;
+pub mod sorted_map;
+
+pub trait Map {
+    type K;
+    type V;
+    fn get(&self, key: &Self::K) -> Option<&Self::V>;
+    fn get_mut(&mut self, key: &Self::K) -> Option<&mut Self::V>;
+    /// Maps `key` to `value`, returning the value it replaced.
+    fn insert(&mut self, key: Self::K, value: Self::V) -> Option<Self::V>;
+    fn remove(&mut self, key: &Self::K) -> Option<Self::V>;
+    /// The value for `key`, first inserting the result of `default` if there is none.
+    fn get_or_insert_with<F: FnOnce() -> Self::V>(&mut self, key: Self::K, default: F) -> &mut Self::V;
+    fn len(&self) -> usize;
+    fn iter(&self) -> Box<dyn Iterator<Item = (&Self::K, &Self::V)> + '_>;
+
+    fn contains_key(&self, key: &Self::K) -> bool {
+        self.get(key).is_some()
+    }
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    fn entry(&mut self, key: Self::K) -> Entry<'_, Self> where Self: Sized {
+        Entry { map: self, key }
+    }
+}
+
+/// A key in a map that may or may not have a value yet.
+pub struct Entry<'a, M: Map> {
+    map: &'a mut M,
+    key: M::K,
+}
+
+impl<'a, M: Map> Entry<'a, M> {
+    pub fn key(&self) -> &M::K {
+        &self.key
+    }
+    pub fn is_occupied(&self) -> bool {
+        self.map.contains_key(&self.key)
+    }
+    pub fn or_insert(self, default: M::V) -> &'a mut M::V {
+        self.map.get_or_insert_with(self.key, || default)
+    }
+    pub fn or_insert_with<F: FnOnce() -> M::V>(self, default: F) -> &'a mut M::V {
+        self.map.get_or_insert_with(self.key, default)
+    }
+    pub fn or_default(self) -> &'a mut M::V where M::V: Default {
+        self.map.get_or_insert_with(self.key, M::V::default)
+    }
+    /// Applies `f` to the value if there is one.
+    pub fn and_modify<F: FnOnce(&mut M::V)>(self, f: F) -> Self {
+        if let Some(value) = self.map.get_mut(&self.key) {
+            f(value);
+        }
+        self
+    }
+}
@@ -0,0 +1,461 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::{Debug, Formatter};
+use std::hash::{BuildHasher, BuildHasherDefault, Hash};
+use std::sync::Arc;
+
+// Hash bits consumed per level
+const BITS: u32 = 5;
+const MASK: u64 = (1 << BITS) - 1;
+
+enum Entry<K, V> {
+    Leaf(u64, Arc<(K, V)>),
+    Node(Arc<Node<K, V>>),
+}
+
+enum Node<K, V> {
+    // One entry per set bit of `bitmap`, in bit order
+    Branch { bitmap: u32, entries: Vec<Entry<K, V>> },
+    // Distinct keys whose full hashes are equal
+    Collision { hash: u64, pairs: Vec<Arc<(K, V)>> },
+}
+
+impl<K, V> Clone for Entry<K, V> {
+    fn clone(&self) -> Self {
+        match self {
+            Entry::Leaf(hash, pair) => Entry::Leaf(*hash, pair.clone()),
+            Entry::Node(node) => Entry::Node(node.clone()),
+        }
+    }
+}
+
+impl<K, V> Clone for Node<K, V> {
+    fn clone(&self) -> Self {
+        match self {
+            Node::Branch { bitmap, entries } => Node::Branch { bitmap: *bitmap, entries: entries.clone() },
+            Node::Collision { hash, pairs } => Node::Collision { hash: *hash, pairs: pairs.clone() },
+        }
+    }
+}
+
+impl<K: Eq, V> Entry<K, V> {
+    fn hash(&self) -> u64 {
+        match self {
+            Entry::Leaf(hash, _) => *hash,
+            Entry::Node(node) => match node.as_ref() {
+                Node::Collision { hash, .. } => *hash,
+                Node::Branch { .. } => unreachable!("Only leaves and collisions are merged"),
+            },
+        }
+    }
+
+    // Combines two entries with distinct keys that landed in the same slot at `shift`.
+    fn merge(existing: Entry<K, V>, incoming: Entry<K, V>, shift: u32) -> Node<K, V> {
+        let (existing_hash, incoming_hash) = (existing.hash(), incoming.hash());
+        if existing_hash == incoming_hash {
+            let mut pairs = match existing {
+                Entry::Leaf(_, pair) => vec![pair],
+                Entry::Node(node) => match node.as_ref() {
+                    Node::Collision { pairs, .. } => pairs.clone(),
+                    Node::Branch { .. } => unreachable!("Only leaves and collisions are merged"),
+                },
+            };
+            if let Entry::Leaf(_, pair) = incoming {
+                pairs.push(pair);
+            }
+            return Node::Collision { hash: existing_hash, pairs };
+        }
+        let (existing_index, incoming_index) = (index(existing_hash, shift), index(incoming_hash, shift));
+        if existing_index == incoming_index {
+            let child = Entry::merge(existing, incoming, shift + BITS);
+            return Node::Branch { bitmap: 1 << existing_index, entries: vec![Entry::Node(Arc::new(child))] };
+        }
+        let bitmap = (1 << existing_index) | (1 << incoming_index);
+        let entries = if existing_index < incoming_index { vec![existing, incoming] } else { vec![incoming, existing] };
+        Node::Branch { bitmap, entries }
+    }
+}
+
+impl<K: Eq, V> Node<K, V> {
+    fn empty() -> Self {
+        Node::Branch { bitmap: 0, entries: Vec::new() }
+    }
+
+    // Whether a key with this hash belongs inside this node rather than beside it.
+    fn can_hold(&self, hash: u64) -> bool {
+        match self {
+            Node::Branch { .. } => true,
+            Node::Collision { hash: collision_hash, .. } => *collision_hash == hash,
+        }
+    }
+
+    fn get(&self, hash: u64, shift: u32, key: &K) -> Option<&(K, V)> {
+        match self {
+            Node::Branch { bitmap, entries } => {
+                let bit = 1 << index(hash, shift);
+                if bitmap & bit == 0 {
+                    return None;
+                }
+                match &entries[position(*bitmap, bit)] {
+                    Entry::Leaf(leaf_hash, pair) => (*leaf_hash == hash && pair.0 == *key).then_some(pair.as_ref()),
+                    Entry::Node(child) => child.get(hash, shift + BITS, key),
+                }
+            }
+            Node::Collision { hash: collision_hash, pairs } => {
+                if *collision_hash != hash {
+                    return None;
+                }
+                pairs.iter().find(|pair| pair.0 == *key).map(|pair| pair.as_ref())
+            }
+        }
+    }
+
+    // Copies the path to the key, returning the new node and whether the key is new.
+    fn insert(&self, hash: u64, shift: u32, pair: Arc<(K, V)>) -> (Node<K, V>, bool) {
+        match self {
+            Node::Branch { bitmap, entries } => {
+                let bit = 1 << index(hash, shift);
+                let position = position(*bitmap, bit);
+                let mut entries = entries.clone();
+                if bitmap & bit == 0 {
+                    entries.insert(position, Entry::Leaf(hash, pair));
+                    return (Node::Branch { bitmap: bitmap | bit, entries }, true);
+                }
+                let (entry, added) = match &entries[position] {
+                    Entry::Leaf(leaf_hash, existing) if *leaf_hash == hash && existing.0 == pair.0 => {
+                        (Entry::Leaf(hash, pair), false)
+                    }
+                    Entry::Node(child) if child.can_hold(hash) => {
+                        let (child, added) = child.insert(hash, shift + BITS, pair);
+                        (Entry::Node(Arc::new(child)), added)
+                    }
+                    existing => {
+                        let merged = Entry::merge(existing.clone(), Entry::Leaf(hash, pair), shift + BITS);
+                        (Entry::Node(Arc::new(merged)), true)
+                    }
+                };
+                entries[position] = entry;
+                (Node::Branch { bitmap: *bitmap, entries }, added)
+            }
+            Node::Collision { hash, pairs } => {
+                let mut pairs = pairs.clone();
+                match pairs.iter().position(|existing| existing.0 == pair.0) {
+                    Some(i) => {
+                        pairs[i] = pair;
+                        (Node::Collision { hash: *hash, pairs }, false)
+                    }
+                    None => {
+                        pairs.push(pair);
+                        (Node::Collision { hash: *hash, pairs }, true)
+                    }
+                }
+            }
+        }
+    }
+
+    // Copies the path to the key without it, or returns None if the key is absent.
+    fn remove(&self, hash: u64, shift: u32, key: &K) -> Option<Node<K, V>> {
+        match self {
+            Node::Branch { bitmap, entries } => {
+                let bit = 1 << index(hash, shift);
+                if bitmap & bit == 0 {
+                    return None;
+                }
+                let position = position(*bitmap, bit);
+                let replacement = match &entries[position] {
+                    Entry::Leaf(leaf_hash, pair) if *leaf_hash == hash && pair.0 == *key => None,
+                    Entry::Leaf(_, _) => return None,
+                    Entry::Node(child) => child.remove(hash, shift + BITS, key)?.into_entry(),
+                };
+                let mut entries = entries.clone();
+                let bitmap = match replacement {
+                    Some(entry) => {
+                        entries[position] = entry;
+                        *bitmap
+                    }
+                    None => {
+                        entries.remove(position);
+                        bitmap & !bit
+                    }
+                };
+                Some(Node::Branch { bitmap, entries })
+            }
+            Node::Collision { hash: collision_hash, pairs } => {
+                if *collision_hash != hash {
+                    return None;
+                }
+                let i = pairs.iter().position(|pair| pair.0 == *key)?;
+                let mut pairs = pairs.clone();
+                pairs.remove(i);
+                Some(Node::Collision { hash, pairs })
+            }
+        }
+    }
+
+    // What a parent should hold in place of this node, keeping the trie canonical: a branch left
+    // with one leaf or collision (or a collision left with one pair) collapses into its parent.
+    fn into_entry(self) -> Option<Entry<K, V>> {
+        match self {
+            Node::Branch { entries, .. } if entries.is_empty() => None,
+            Node::Branch { bitmap, mut entries } if entries.len() == 1 => match &entries[0] {
+                Entry::Node(child) if matches!(child.as_ref(), Node::Branch { .. }) => {
+                    Some(Entry::Node(Arc::new(Node::Branch { bitmap, entries })))
+                }
+                _ => entries.pop(),
+            },
+            Node::Collision { hash, mut pairs } if pairs.len() == 1 => pairs.pop().map(|pair| Entry::Leaf(hash, pair)),
+            node => Some(Entry::Node(Arc::new(node))),
+        }
+    }
+}
+
+impl<K: Eq, V: PartialEq> Node<K, V> {
+    // Relies on both tries being canonical, skipping any subtrees the two versions share.
+    fn equals(&self, other: &Node<K, V>) -> bool {
+        match (self, other) {
+            (Node::Branch { bitmap: a_bitmap, entries: a }, Node::Branch { bitmap: b_bitmap, entries: b }) => {
+                a_bitmap == b_bitmap && a.iter().zip(b).all(|pair| match pair {
+                    (Entry::Leaf(a_hash, a), Entry::Leaf(b_hash, b)) => {
+                        Arc::ptr_eq(a, b) || (a_hash == b_hash && a.0 == b.0 && a.1 == b.1)
+                    }
+                    (Entry::Node(a), Entry::Node(b)) => Arc::ptr_eq(a, b) || a.equals(b),
+                    _ => false,
+                })
+            }
+            (Node::Collision { hash: a_hash, pairs: a }, Node::Collision { hash: b_hash, pairs: b }) => {
+                a_hash == b_hash && a.len() == b.len() && a.iter().all(|a| b.iter().any(|b| a.0 == b.0 && a.1 == b.1))
+            }
+            _ => false,
+        }
+    }
+}
+
+fn index(hash: u64, shift: u32) -> u32 {
+    ((hash >> shift) & MASK) as u32
+}
+
+fn position(bitmap: u32, bit: u32) -> usize {
+    (bitmap & (bit - 1)).count_ones() as usize
+}
+
+/// An immutable hash map built as a hash array mapped trie.
+///
+/// `insert` and `remove` return a new version that shares every untouched subtree with the old
+/// one, so keeping many versions around costs O(log n) per change rather than a full copy. Every
+/// map hashes keys the same way and keeps its trie in a canonical shape, which lets equality
+/// checks skip any subtrees two versions share.
+pub struct PersistentMap<K, V> {
+    root: Arc<Node<K, V>>,
+    len: usize,
+}
+
+impl<K: Hash + Eq, V> PersistentMap<K, V> {
+    pub fn new() -> Self {
+        PersistentMap { root: Arc::new(Node::empty()), len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.root.get(hash(key), 0, key).map(|(_, value)| value)
+    }
+    pub fn get_key_value(&self, key: &K) -> Option<(&K, &V)> {
+        self.root.get(hash(key), 0, key).map(|(key, value)| (key, value))
+    }
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+    /// A new version with `key` mapped to `value`.
+    pub fn insert(&self, key: K, value: V) -> Self {
+        let (root, added) = self.root.insert(hash(&key), 0, Arc::new((key, value)));
+        PersistentMap { root: Arc::new(root), len: self.len + added as usize }
+    }
+    /// A new version without `key`, sharing everything with this one if `key` is absent.
+    pub fn remove(&self, key: &K) -> Self {
+        match self.root.remove(hash(key), 0, key) {
+            Some(root) => PersistentMap { root: Arc::new(root), len: self.len - 1 },
+            None => self.clone(),
+        }
+    }
+    /// Whether both are the very same version, in O(1).
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.root, &other.root)
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter { stack: vec![(self.root.as_ref(), 0)], remaining: self.len }
+    }
+    pub fn keys(&self) -> impl Iterator<Item = &K> + '_ {
+        self.iter().map(|(key, _)| key)
+    }
+    pub fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.iter().map(|(_, value)| value)
+    }
+}
+
+fn hash<K: Hash>(key: &K) -> u64 {
+    BuildHasherDefault::<DefaultHasher>::default().hash_one(key)
+}
+
+pub struct Iter<'a, K, V> {
+    stack: Vec<(&'a Node<K, V>, usize)>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, i)) = self.stack.pop() {
+            match node {
+                Node::Branch { entries, .. } if i < entries.len() => {
+                    self.stack.push((node, i + 1));
+                    match &entries[i] {
+                        Entry::Leaf(_, pair) => {
+                            self.remaining -= 1;
+                            return Some((&pair.0, &pair.1));
+                        }
+                        Entry::Node(child) => self.stack.push((child.as_ref(), 0)),
+                    }
+                }
+                Node::Collision { pairs, .. } if i < pairs.len() => {
+                    self.stack.push((node, i + 1));
+                    self.remaining -= 1;
+                    return Some((&pairs[i].0, &pairs[i].1));
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> Clone for PersistentMap<K, V> {
+    fn clone(&self) -> Self {
+        PersistentMap { root: self.root.clone(), len: self.len }
+    }
+}
+
+impl<K: Hash + Eq, V> Default for PersistentMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq, V: PartialEq> PartialEq for PersistentMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && (self.ptr_eq(other) || self.root.equals(&other.root))
+    }
+}
+
+impl<K: Hash + Eq + Debug, V: Debug> Debug for PersistentMap<K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Hash + Eq, V> FromIterator<(K, V)> for PersistentMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        iter.into_iter().fold(PersistentMap::new(), |map, (key, value)| map.insert(key, value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::hash::{Hash, Hasher};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use super::PersistentMap;
+
+    // Every key hashes alike, forcing collision nodes
+    #[derive(Debug, PartialEq, Eq)]
+    struct Colliding(u32);
+
+    impl Hash for Colliding {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            0.hash(state)
+        }
+    }
+
+    #[test]
+    fn test_insert_get_remove() {
+        let empty: PersistentMap<&str, u32> = PersistentMap::new();
+        let one = empty.insert("a", 1);
+        let two = one.insert("b", 2);
+        let replaced = two.insert("a", 10);
+
+        assert_eq!(empty.len(), 0);
+        assert_eq!(one.get(&"a"), Some(&1));
+        assert_eq!(one.get(&"b"), None);
+        assert_eq!(two.len(), 2);
+        assert_eq!(replaced.len(), 2);
+        assert_eq!(replaced.get(&"a"), Some(&10));
+        assert_eq!(two.get(&"a"), Some(&1));
+
+        let removed = replaced.remove(&"a");
+        assert_eq!(removed.len(), 1);
+        assert!(!removed.contains_key(&"a"));
+        assert!(replaced.contains_key(&"a"));
+        assert!(removed.remove(&"z").ptr_eq(&removed));
+    }
+
+    #[test]
+    fn test_versions_match_std() {
+        let mut rng = StdRng::seed_from_u64(33);
+        let mut map: PersistentMap<u32, u32> = PersistentMap::new();
+        let mut expected: HashMap<u32, u32> = HashMap::new();
+        let mut versions = Vec::new();
+        for step in 0..5_000 {
+            let key = rng.gen_range(0..1_000);
+            if rng.gen_bool(0.6) {
+                map = map.insert(key, step);
+                expected.insert(key, step);
+            } else {
+                map = map.remove(&key);
+                expected.remove(&key);
+            }
+            if step % 1_000 == 0 {
+                versions.push((map.clone(), expected.clone()));
+            }
+        }
+        versions.push((map, expected));
+        for (map, expected) in versions {
+            assert_eq!(map.len(), expected.len());
+            assert_eq!(map.iter().count(), expected.len());
+            assert!(map.iter().all(|(key, value)| expected.get(key) == Some(value)));
+        }
+    }
+
+    #[test]
+    fn test_collisions() {
+        let map: PersistentMap<Colliding, u32> = (0..4).map(|i| (Colliding(i), i)).collect();
+        assert_eq!(map.len(), 4);
+        assert!((0..4).all(|i| map.get(&Colliding(i)) == Some(&i)));
+
+        let smaller = map.remove(&Colliding(1)).remove(&Colliding(2)).remove(&Colliding(3));
+        assert_eq!(smaller.get(&Colliding(0)), Some(&0));
+        assert_eq!(smaller, PersistentMap::new().insert(Colliding(0), 0));
+    }
+
+    #[test]
+    fn test_equality_is_structural() {
+        let a: PersistentMap<u32, u32> = (0..500).map(|i| (i, i)).collect();
+        let b: PersistentMap<u32, u32> = (0..500).rev().map(|i| (i, i)).collect();
+        assert!(!a.ptr_eq(&b));
+        assert_eq!(a, b);
+
+        let c = (500..1_000).fold(a.clone(), |map, i| map.insert(i, i));
+        let c = (500..1_000).fold(c, |map, i| map.remove(&i));
+        assert_eq!(a, c);
+        assert_ne!(a, a.insert(0, 1));
+        assert_ne!(a, a.remove(&0));
+    }
+}
@@ -0,0 +1,546 @@
+use std::cmp::Ordering;
+use std::fmt::{Debug, Formatter};
+use std::mem;
+use std::ops::{Bound, RangeBounds};
+use rand::Rng;
+use crate::map::Map;
+use crate::set::sorted_set::SortedSet;
+
+const MAX_LEVEL: usize = 24;
+// Index of the sentinel node that starts every level
+const HEAD: usize = 0;
+// Stands in for a missing link
+const NIL: usize = usize::MAX;
+
+struct Node<K, V> {
+    // None only for the head sentinel
+    entry: Option<(K, V)>,
+    next: Vec<usize>,
+    // Number of level-0 links skipped by next[l]; a NIL link reaches one past the last entry
+    width: Vec<usize>,
+    prev: usize,
+}
+
+/// An ordered map backed by an indexable skip list.
+///
+/// Nodes live in one arena and link to each other by index. Each link records how many entries
+/// it skips, which gives O(log n) `rank` and `select` on top of the usual ordered lookups.
+pub struct SortedMap<K: Ord, V> {
+    nodes: Vec<Node<K, V>>,
+    // Arena slots released by removals
+    free: Vec<usize>,
+    tail: usize,
+    len: usize,
+}
+
+impl<K: Ord, V> SortedMap<K, V> {
+    pub fn new() -> Self {
+        let head = Node {
+            entry: None,
+            next: vec![NIL; MAX_LEVEL],
+            width: vec![1; MAX_LEVEL],
+            prev: NIL,
+        };
+        SortedMap {
+            nodes: vec![head],
+            free: Vec::new(),
+            tail: NIL,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Maps `key` to `value`, returning the value it replaced. An existing key is kept as is.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let (update, rank) = self.search(|k| k.cmp(&key));
+        let next = self.nodes[update[0]].next[0];
+        if next != NIL && self.key(next) == &key {
+            return self.value_mut(next).map(|existing| mem::replace(existing, value));
+        }
+        self.link(&update, &rank, key, value);
+        None
+    }
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.get_key_value(key).map(|(_, value)| value)
+    }
+    pub fn get_key_value(&self, key: &K) -> Option<(&K, &V)> {
+        let node = self.find(key)?;
+        self.entry_at(node)
+    }
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let node = self.find(key)?;
+        self.value_mut(node)
+    }
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.find(key).is_some()
+    }
+    /// The value for `key`, first inserting the result of `default` if there is none.
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, default: F) -> &mut V {
+        let (update, rank) = self.search(|k| k.cmp(&key));
+        let next = self.nodes[update[0]].next[0];
+        let node = if next != NIL && self.key(next) == &key {
+            next
+        } else {
+            self.link(&update, &rank, key, default())
+        };
+        self.value_mut(node).expect("Only the head has no entry")
+    }
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+    pub fn remove_entry(&mut self, key: &K) -> Option<(K, V)> {
+        let (update, _) = self.search(|k| k.cmp(key));
+        let node = self.nodes[update[0]].next[0];
+        if node == NIL || self.key(node) != key {
+            return None;
+        }
+        Some(self.unlink(&update, node))
+    }
+    pub fn clear(&mut self) {
+        self.nodes.truncate(1);
+        self.nodes[HEAD].next.fill(NIL);
+        self.nodes[HEAD].width.fill(1);
+        self.free.clear();
+        self.tail = NIL;
+        self.len = 0;
+    }
+
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        self.entry_at(self.nodes[HEAD].next[0])
+    }
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        self.entry_at(self.tail)
+    }
+    pub fn pop_first(&mut self) -> Option<(K, V)> {
+        let first = self.nodes[HEAD].next[0];
+        if first == NIL {
+            return None;
+        }
+        Some(self.unlink(&[HEAD; MAX_LEVEL], first))
+    }
+    pub fn pop_last(&mut self) -> Option<(K, V)> {
+        let last = self.tail;
+        if last == NIL {
+            return None;
+        }
+        let (update, _) = self.search(|k| k.cmp(self.key(last)));
+        Some(self.unlink(&update, last))
+    }
+    /// The entry with the greatest key less than or equal to `key`.
+    pub fn floor(&self, key: &K) -> Option<(&K, &V)> {
+        let (update, _) = self.search(|k| k.cmp(key).then(Ordering::Less));
+        self.entry_at(update[0])
+    }
+    /// The entry with the least key greater than or equal to `key`.
+    pub fn ceiling(&self, key: &K) -> Option<(&K, &V)> {
+        let (update, _) = self.search(|k| k.cmp(key));
+        self.entry_at(self.nodes[update[0]].next[0])
+    }
+    /// Number of keys strictly less than `key`.
+    pub fn rank(&self, key: &K) -> usize {
+        self.search(|k| k.cmp(key)).1[0]
+    }
+    /// The entry at `index` in ascending key order.
+    pub fn select(&self, index: usize) -> Option<(&K, &V)> {
+        if index >= self.len {
+            return None;
+        }
+        let target = index + 1;
+        let mut node = HEAD;
+        let mut position = 0;
+        for l in (0..MAX_LEVEL).rev() {
+            while self.nodes[node].next[l] != NIL && position + self.nodes[node].width[l] <= target {
+                position += self.nodes[node].width[l];
+                node = self.nodes[node].next[l];
+            }
+        }
+        self.entry_at(node)
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            map: self,
+            front: self.nodes[HEAD].next[0],
+            back: self.tail,
+            remaining: self.len,
+        }
+    }
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &K> + ExactSizeIterator + '_ {
+        self.iter().map(|(key, _)| key)
+    }
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> + ExactSizeIterator + '_ {
+        self.iter().map(|(_, value)| value)
+    }
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> Iter<'_, K, V> {
+        let (start_update, start_rank) = match range.start_bound() {
+            Bound::Included(start) => self.search(|k| k.cmp(start)),
+            Bound::Excluded(start) => self.search(|k| k.cmp(start).then(Ordering::Less)),
+            Bound::Unbounded => ([HEAD; MAX_LEVEL], [0; MAX_LEVEL]),
+        };
+        let (end_update, end_rank) = match range.end_bound() {
+            Bound::Included(end) => self.search(|k| k.cmp(end).then(Ordering::Less)),
+            Bound::Excluded(end) => self.search(|k| k.cmp(end)),
+            Bound::Unbounded => ([self.tail_or_head(); MAX_LEVEL], [self.len; MAX_LEVEL]),
+        };
+        Iter {
+            map: self,
+            front: self.nodes[start_update[0]].next[0],
+            back: end_update[0],
+            remaining: end_rank[0].saturating_sub(start_rank[0]),
+        }
+    }
+
+    /// The keys as a `SortedSet`, reusing the skip list instead of rebuilding it.
+    pub fn into_key_set(self) -> SortedSet<K> {
+        SortedSet::from(self.map_values(|_| ()))
+    }
+    pub fn key_set(&self) -> SortedSet<K> where K: Clone {
+        SortedSet::from(SortedMap::from_sorted(self.keys().map(|key| (key.clone(), ()))))
+    }
+    /// Transforms every value in place, keeping the skip list.
+    pub fn map_values<U, F: FnMut(V) -> U>(self, mut f: F) -> SortedMap<K, U> {
+        SortedMap {
+            nodes: self.nodes.into_iter()
+                .map(|node| Node {
+                    entry: node.entry.map(|(key, value)| (key, f(value))),
+                    next: node.next,
+                    width: node.width,
+                    prev: node.prev,
+                })
+                .collect(),
+            free: self.free,
+            tail: self.tail,
+            len: self.len,
+        }
+    }
+
+    /// Builds a map in O(n) from entries in strictly ascending key order.
+    pub(crate) fn from_sorted<I: IntoIterator<Item = (K, V)>>(entries: I) -> Self {
+        let mut map = SortedMap::new();
+        let mut last = [HEAD; MAX_LEVEL];
+        let mut rank = [0; MAX_LEVEL];
+        for (key, value) in entries {
+            let height = Self::random_height();
+            let position = map.len + 1;
+            let node = map.allocate(key, value, height);
+            let before = last[0];
+            for l in 0..MAX_LEVEL {
+                if l < height {
+                    map.nodes[last[l]].next[l] = node;
+                    map.nodes[last[l]].width[l] = position - rank[l];
+                    map.nodes[node].width[l] = 1;
+                    last[l] = node;
+                    rank[l] = position;
+                } else {
+                    map.nodes[last[l]].width[l] += 1;
+                }
+            }
+            map.link_prev(before, node);
+            map.len += 1;
+        }
+        map
+    }
+
+    // For every level, finds the last node whose key orders `Less` under `cmp`, along with that
+    // node's 1-based position (the head is position 0).
+    fn search<F: Fn(&K) -> Ordering>(&self, cmp: F) -> ([usize; MAX_LEVEL], [usize; MAX_LEVEL]) {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut rank = [0; MAX_LEVEL];
+        let mut node = HEAD;
+        let mut position = 0;
+        for l in (0..MAX_LEVEL).rev() {
+            loop {
+                let next = self.nodes[node].next[l];
+                if next == NIL || cmp(self.key(next)) != Ordering::Less {
+                    break;
+                }
+                position += self.nodes[node].width[l];
+                node = next;
+            }
+            update[l] = node;
+            rank[l] = position;
+        }
+        (update, rank)
+    }
+    fn find(&self, key: &K) -> Option<usize> {
+        let (update, _) = self.search(|k| k.cmp(key));
+        let next = self.nodes[update[0]].next[0];
+        (next != NIL && self.key(next) == key).then_some(next)
+    }
+    // Inserts a new node after the predecessors `search` found, returning it.
+    fn link(&mut self, update: &[usize; MAX_LEVEL], rank: &[usize; MAX_LEVEL], key: K, value: V) -> usize {
+        let height = Self::random_height();
+        let position = rank[0] + 1;
+        let node = self.allocate(key, value, height);
+        for l in 0..MAX_LEVEL {
+            let before = update[l];
+            if l < height {
+                self.nodes[node].next[l] = self.nodes[before].next[l];
+                self.nodes[node].width[l] = rank[l] + self.nodes[before].width[l] + 1 - position;
+                self.nodes[before].next[l] = node;
+                self.nodes[before].width[l] = position - rank[l];
+            } else {
+                self.nodes[before].width[l] += 1;
+            }
+        }
+        self.link_prev(update[0], node);
+        self.len += 1;
+        node
+    }
+    fn unlink(&mut self, update: &[usize; MAX_LEVEL], node: usize) -> (K, V) {
+        let height = self.nodes[node].next.len();
+        for (l, &before) in update.iter().enumerate() {
+            if l < height {
+                self.nodes[before].next[l] = self.nodes[node].next[l];
+                self.nodes[before].width[l] += self.nodes[node].width[l] - 1;
+            } else {
+                self.nodes[before].width[l] -= 1;
+            }
+        }
+        let (prev, next) = (self.nodes[node].prev, self.nodes[node].next[0]);
+        if next == NIL {
+            self.tail = prev;
+        } else {
+            self.nodes[next].prev = prev;
+        }
+        self.len -= 1;
+        self.free.push(node);
+        self.nodes[node].entry.take().expect("Only the head has no entry")
+    }
+    fn link_prev(&mut self, before: usize, node: usize) {
+        self.nodes[node].prev = if before == HEAD { NIL } else { before };
+        match self.nodes[node].next[0] {
+            NIL => self.tail = node,
+            next => self.nodes[next].prev = node,
+        }
+    }
+    fn allocate(&mut self, key: K, value: V, height: usize) -> usize {
+        let node = Node {
+            entry: Some((key, value)),
+            next: vec![NIL; height],
+            width: vec![0; height],
+            prev: NIL,
+        };
+        match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+    fn random_height() -> usize {
+        let mut rng = rand::thread_rng();
+        let mut height = 1;
+        while height < MAX_LEVEL && rng.gen_bool(0.5) {
+            height += 1;
+        }
+        height
+    }
+    fn key(&self, node: usize) -> &K {
+        &self.nodes[node].entry.as_ref().expect("Only the head has no entry").0
+    }
+    fn value_mut(&mut self, node: usize) -> Option<&mut V> {
+        self.nodes[node].entry.as_mut().map(|(_, value)| value)
+    }
+    fn entry_at(&self, node: usize) -> Option<(&K, &V)> {
+        if node == NIL {
+            return None;
+        }
+        self.nodes[node].entry.as_ref().map(|(key, value)| (key, value))
+    }
+    fn tail_or_head(&self) -> usize {
+        if self.tail == NIL { HEAD } else { self.tail }
+    }
+}
+
+pub struct Iter<'a, K: Ord, V> {
+    map: &'a SortedMap<K, V>,
+    front: usize,
+    back: usize,
+    remaining: usize,
+}
+
+impl<'a, K: Ord, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = self.front;
+        self.front = self.map.nodes[node].next[0];
+        self.remaining -= 1;
+        self.map.entry_at(node)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: Ord, V> DoubleEndedIterator for Iter<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = self.back;
+        self.back = self.map.nodes[node].prev;
+        self.remaining -= 1;
+        self.map.entry_at(node)
+    }
+}
+
+impl<K: Ord, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<K: Ord, V> Default for SortedMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Clone for SortedMap<K, V> {
+    fn clone(&self) -> Self {
+        SortedMap::from_sorted(self.iter().map(|(key, value)| (key.clone(), value.clone())))
+    }
+}
+
+impl<K: Ord + Debug, V: Debug> Debug for SortedMap<K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for SortedMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut entries: Vec<(K, V)> = iter.into_iter().collect();
+        // Stable, so the last value given for a key is the one that survives
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut deduped: Vec<(K, V)> = Vec::with_capacity(entries.len());
+        for entry in entries {
+            match deduped.last_mut() {
+                Some(last) if last.0 == entry.0 => last.1 = entry.1,
+                _ => deduped.push(entry),
+            }
+        }
+        SortedMap::from_sorted(deduped)
+    }
+}
+
+impl<K: Ord, V> Extend<(K, V)> for SortedMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K: Ord, V> Map for SortedMap<K, V> {
+    type K = K;
+    type V = V;
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.get(key)
+    }
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.get_mut(key)
+    }
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert(key, value)
+    }
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove(key)
+    }
+    fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, default: F) -> &mut V {
+        self.get_or_insert_with(key, default)
+    }
+    fn len(&self) -> usize {
+        self.len
+    }
+    fn iter(&self) -> Box<dyn Iterator<Item = (&K, &V)> + '_> {
+        Box::new(self.iter())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use crate::map::Map;
+    use super::SortedMap;
+
+    #[test]
+    fn test_random_operations_match_btree_map() {
+        let mut rng = StdRng::seed_from_u64(34);
+        let mut map: SortedMap<u32, u32> = SortedMap::new();
+        let mut expected: BTreeMap<u32, u32> = BTreeMap::new();
+        for step in 0..5_000 {
+            let key = rng.gen_range(0..500);
+            if rng.gen_bool(0.6) {
+                assert_eq!(map.insert(key, step), expected.insert(key, step));
+            } else {
+                assert_eq!(map.remove(&key), expected.remove(&key));
+            }
+        }
+        assert_eq!(map.len(), expected.len());
+        assert!(map.iter().eq(expected.iter()));
+        assert!(map.iter().rev().eq(expected.iter().rev()));
+        assert!(map.range(100..200).eq(expected.range(100..200)));
+        for (index, (key, value)) in expected.iter().enumerate() {
+            assert_eq!(map.select(index), Some((key, value)));
+            assert_eq!(map.rank(key), index);
+        }
+    }
+
+    #[test]
+    fn test_ordered_queries() {
+        let mut map: SortedMap<u32, &str> = [(10, "ten"), (20, "twenty"), (30, "thirty")].into_iter().collect();
+        assert_eq!(map.first_key_value(), Some((&10, &"ten")));
+        assert_eq!(map.last_key_value(), Some((&30, &"thirty")));
+        assert_eq!(map.floor(&25), Some((&20, &"twenty")));
+        assert_eq!(map.ceiling(&25), Some((&30, &"thirty")));
+        assert_eq!(map.pop_first(), Some((10, "ten")));
+        assert_eq!(map.pop_last(), Some((30, "thirty")));
+        assert_eq!(map.keys().collect::<Vec<_>>(), vec![&20]);
+    }
+
+    #[test]
+    fn test_entry_and_get_mut() {
+        let mut map: SortedMap<&str, Vec<u32>> = SortedMap::new();
+        map.entry("b").or_default().push(1);
+        map.entry("a").or_default().push(2);
+        map.entry("b").or_default().push(3);
+        map.get_mut(&"a").unwrap().push(4);
+
+        assert_eq!(map.get(&"a"), Some(&vec![2, 4]));
+        assert_eq!(map.get(&"b"), Some(&vec![1, 3]));
+        assert_eq!(map.keys().collect::<Vec<_>>(), vec![&"a", &"b"]);
+    }
+
+    #[test]
+    fn test_from_iter_keeps_last_value() {
+        let map: SortedMap<u32, u32> = [(1, 1), (2, 2), (1, 3)].into_iter().collect();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&1), Some(&3));
+    }
+
+    #[test]
+    fn test_key_set() {
+        let map: SortedMap<u32, &str> = [(2, "two"), (1, "one"), (3, "three")].into_iter().collect();
+        let keys = map.key_set();
+        assert_eq!(keys.iter().collect::<Vec<_>>(), vec![&1, &2, &3]);
+
+        let keys = map.into_key_set();
+        assert_eq!(keys.select(1), Some(&2));
+        assert!(keys.contains(&3) && !keys.contains(&4));
+    }
+}
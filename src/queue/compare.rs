@@ -0,0 +1,67 @@
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+
+/// Decides the order in which a queue serves its elements: greater elements come out first.
+///
+/// Queues are generic over their order, so a comparison compiles down to a direct call;
+/// `MaxOrder` and `MinOrder` take no space and cost nothing over calling `Ord` directly.
+pub trait Compare<T: ?Sized> {
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+}
+
+/// Serves the largest element first, by `Ord`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MaxOrder;
+
+impl<T: Ord + ?Sized> Compare<T> for MaxOrder {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+/// Flips another order, e.g. serving the smallest element first.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Reversed<C>(pub C);
+
+impl<T: ?Sized, C: Compare<T>> Compare<T> for Reversed<C> {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self.0.compare(b, a)
+    }
+}
+
+/// Serves the smallest element first, by `Ord`.
+pub type MinOrder = Reversed<MaxOrder>;
+
+/// Orders by a comparator function.
+#[derive(Clone, Copy)]
+pub struct FnOrder<F>(pub F);
+
+impl<T: ?Sized, F: Fn(&T, &T) -> Ordering> Compare<T> for FnOrder<F> {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        (self.0)(a, b)
+    }
+}
+
+/// Orders by a key extracted from each element, largest key first.
+pub struct KeyOrder<F, K> {
+    key: F,
+    _key: PhantomData<fn() -> K>,
+}
+
+impl<F, K> KeyOrder<F, K> {
+    pub fn new(key: F) -> Self {
+        KeyOrder { key, _key: PhantomData }
+    }
+}
+
+impl<F: Clone, K> Clone for KeyOrder<F, K> {
+    fn clone(&self) -> Self {
+        KeyOrder::new(self.key.clone())
+    }
+}
+
+impl<T: ?Sized, K: Ord, F: Fn(&T) -> K> Compare<T> for KeyOrder<F, K> {
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        (self.key)(a).cmp(&(self.key)(b))
+    }
+}
@@ -0,0 +1,252 @@
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use crate::queue::compare::{Compare, MaxOrder};
+use crate::queue::PriorityQueue;
+use crate::Error;
+
+/// The element a push handed back because the queue was closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Closed<T>(pub T);
+
+struct State<T, C> {
+    queue: PriorityQueue<T, C>,
+    closed: bool,
+}
+
+/// A `PriorityQueue` that producer and consumer threads share by reference.
+///
+/// Consumers can wait for an element with `pop` or `pop_timeout`. With a capacity, `push`
+/// waits for room instead of growing the queue. `close` wakes every waiter: pushes fail from
+/// then on, while pops drain what is left and then return `None`.
+pub struct ConcurrentPriorityQueue<T, C = MaxOrder> {
+    state: Mutex<State<T, C>>,
+    // Signalled when an element arrives or the queue closes
+    not_empty: Condvar,
+    // Signalled when an element leaves or the queue closes
+    not_full: Condvar,
+    capacity: Option<usize>,
+}
+
+impl<T: Ord> ConcurrentPriorityQueue<T> {
+    /// An unbounded queue serving the largest element first.
+    pub fn new() -> Self {
+        Self::with_order(MaxOrder)
+    }
+    /// A queue serving the largest element first that holds at most `capacity` elements.
+    pub fn bounded(capacity: usize) -> crate::Result<Self> {
+        Self::bounded_with_order(capacity, MaxOrder)
+    }
+}
+
+impl<T, C: Compare<T>> ConcurrentPriorityQueue<T, C> {
+    pub fn with_order(order: C) -> Self {
+        Self::build(order, None)
+    }
+    pub fn bounded_with_order(capacity: usize, order: C) -> crate::Result<Self> {
+        if capacity == 0 {
+            return Err(Error::IllegalArguments("A bounded queue needs room for at least one element.".to_string()));
+        }
+        Ok(Self::build(order, Some(capacity)))
+    }
+    fn build(order: C, capacity: Option<usize>) -> Self {
+        ConcurrentPriorityQueue {
+            state: Mutex::new(State { queue: PriorityQueue::with_order(order), closed: false }),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+    pub fn len(&self) -> usize {
+        self.lock().queue.size()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.lock().queue.is_empty()
+    }
+    pub fn is_closed(&self) -> bool {
+        self.lock().closed
+    }
+
+    /// Queues `item`, first waiting for room if the queue is bounded and full. Hands `item` back
+    /// if the queue is or becomes closed.
+    pub fn push(&self, item: T) -> Result<(), Closed<T>> {
+        let state = self.lock();
+        let mut state = self.not_full
+            .wait_while(state, |state| !state.closed && self.is_full(state))
+            .expect("A thread panicked while holding the queue");
+        if state.closed {
+            return Err(Closed(item));
+        }
+        state.queue.push(item);
+        drop(state);
+        self.not_empty.notify_one();
+        Ok(())
+    }
+    /// Takes the top element if there is one, without waiting.
+    pub fn try_pop(&self) -> Option<T> {
+        let item = self.lock().queue.pop();
+        self.taken(item)
+    }
+    /// Takes the top element, waiting for one to arrive. Returns `None` once the queue is closed
+    /// and empty.
+    pub fn pop(&self) -> Option<T> {
+        let state = self.lock();
+        let item = self.not_empty
+            .wait_while(state, |state| !state.closed && state.queue.is_empty())
+            .expect("A thread panicked while holding the queue")
+            .queue
+            .pop();
+        self.taken(item)
+    }
+    /// Like `pop`, but gives up and returns `None` after waiting `timeout`.
+    pub fn pop_timeout(&self, timeout: Duration) -> Option<T> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.lock();
+        while !state.closed && state.queue.is_empty() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return None;
+            }
+            state = self.not_empty
+                .wait_timeout(state, remaining)
+                .expect("A thread panicked while holding the queue")
+                .0;
+        }
+        let item = state.queue.pop();
+        drop(state);
+        self.taken(item)
+    }
+    /// Stops accepting elements and wakes every waiting thread.
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.not_empty.notify_all();
+        self.not_full.notify_all();
+    }
+    /// Takes every queued element, in the order they would have been popped.
+    pub fn drain(&self) -> Vec<T> {
+        let items: Vec<T> = self.lock().queue.drain_sorted().collect();
+        self.not_full.notify_all();
+        items
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State<T, C>> {
+        self.state.lock().expect("A thread panicked while holding the queue")
+    }
+    fn is_full(&self, state: &State<T, C>) -> bool {
+        self.capacity.is_some_and(|capacity| state.queue.size() >= capacity)
+    }
+    // Lets a waiting producer into the room an element left.
+    fn taken(&self, item: Option<T>) -> Option<T> {
+        if item.is_some() && self.capacity.is_some() {
+            self.not_full.notify_one();
+        }
+        item
+    }
+}
+
+impl<T: Ord> Default for ConcurrentPriorityQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use super::{Closed, ConcurrentPriorityQueue};
+
+    #[test]
+    fn test_single_thread() {
+        let queue = ConcurrentPriorityQueue::new();
+        assert_eq!(queue.try_pop(), None);
+        for x in [5, 1, 8] {
+            queue.push(x).unwrap();
+        }
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.pop(), Some(8));
+        assert_eq!(queue.pop_timeout(Duration::from_millis(1)), Some(5));
+        queue.close();
+        assert!(queue.is_closed());
+        assert_eq!(queue.push(9), Err(Closed(9)));
+        assert_eq!(queue.pop(), Some(1));
+        assert_eq!(queue.pop(), None);
+        assert!(ConcurrentPriorityQueue::<u32>::bounded(0).is_err());
+    }
+
+    #[test]
+    fn test_pop_timeout_gives_up() {
+        let queue: ConcurrentPriorityQueue<u32> = ConcurrentPriorityQueue::new();
+        let start = Instant::now();
+        assert_eq!(queue.pop_timeout(Duration::from_millis(20)), None);
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn test_close_wakes_waiting_consumers() {
+        let queue: ConcurrentPriorityQueue<u32> = ConcurrentPriorityQueue::new();
+        thread::scope(|scope| {
+            let consumers: Vec<_> = (0..4).map(|_| scope.spawn(|| queue.pop())).collect();
+            thread::sleep(Duration::from_millis(20));
+            queue.close();
+            for consumer in consumers {
+                assert_eq!(consumer.join().unwrap(), None);
+            }
+        });
+    }
+
+    #[test]
+    fn test_bounded_blocks_producers() {
+        let queue = ConcurrentPriorityQueue::bounded(2).unwrap();
+        queue.push(1).unwrap();
+        queue.push(2).unwrap();
+        thread::scope(|scope| {
+            let producer = scope.spawn(|| queue.push(3));
+            thread::sleep(Duration::from_millis(20));
+            // The producer is still waiting for room
+            assert_eq!(queue.len(), 2);
+            assert_eq!(queue.pop(), Some(2));
+            producer.join().unwrap().unwrap();
+        });
+        assert_eq!(queue.drain(), vec![3, 1]);
+
+        queue.push(1).unwrap();
+        queue.push(2).unwrap();
+        thread::scope(|scope| {
+            let producer = scope.spawn(|| queue.push(3));
+            thread::sleep(Duration::from_millis(20));
+            queue.close();
+            assert_eq!(producer.join().unwrap(), Err(Closed(3)));
+        });
+    }
+
+    #[test]
+    fn test_producers_and_consumers() {
+        let queue = ConcurrentPriorityQueue::bounded(16).unwrap();
+        let mut consumed = thread::scope(|scope| {
+            let consumers: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| std::iter::from_fn(|| queue.pop()).collect::<Vec<u32>>()))
+                .collect();
+            let producers: Vec<_> = (0..4)
+                .map(|p| {
+                    let queue = &queue;
+                    scope.spawn(move || {
+                        for i in 0..1_000 {
+                            queue.push(p * 1_000 + i).unwrap();
+                        }
+                    })
+                })
+                .collect();
+            for producer in producers {
+                producer.join().unwrap();
+            }
+            queue.close();
+            consumers.into_iter().flat_map(|consumer| consumer.join().unwrap()).collect::<Vec<u32>>()
+        });
+        consumed.sort();
+        assert_eq!(consumed, (0..4_000).collect::<Vec<u32>>());
+    }
+}
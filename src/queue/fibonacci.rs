@@ -0,0 +1,428 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::fmt::{Debug, Formatter};
+use std::mem;
+use std::rc::{Rc, Weak};
+use crate::queue::compare::{Compare, MaxOrder, MinOrder, Reversed};
+use crate::queue::{self, AddressableHeap, Heap, HeapNode, HeapOwner, Slot};
+use crate::Error;
+
+/// Where a `FibonacciHeap` keeps an element, for its `Handle`s to refer to.
+pub struct Node<T> {
+    item: T,
+    // Shared with the element's handles and with its children, which reach it as their parent
+    slot: Slot<Node<T>>,
+    links: RefCell<Links<T>>,
+}
+
+struct Links<T> {
+    // The parent's slot; `None` for roots
+    parent: Option<Slot<Node<T>>>,
+    // Head of the child list
+    child: Option<Rc<Node<T>>>,
+    // Neighbours in the root list or the parent's child list; `prev` dangles at the head
+    next: Option<Rc<Node<T>>>,
+    prev: Weak<Node<T>>,
+    degree: usize,
+    // Lost a child since it last became a child itself
+    marked: bool,
+}
+
+impl<T> Links<T> {
+    fn new() -> Self {
+        Links { parent: None, child: None, next: None, prev: Weak::new(), degree: 0, marked: false }
+    }
+}
+
+impl<T> Node<T> {
+    fn new(item: T, slot: Slot<Node<T>>) -> Rc<Self> {
+        let node = Rc::new(Node { item, slot, links: RefCell::new(Links::new()) });
+        *node.slot.borrow_mut() = Rc::downgrade(&node);
+        node
+    }
+    fn parent(&self) -> Option<Rc<Node<T>>> {
+        self.links.borrow().parent.as_ref().map(|slot| slot.borrow().upgrade().expect("A parent slot points at a queued node"))
+    }
+}
+
+impl<T> HeapNode for Node<T> {
+    type Item = T;
+
+    fn into_item(self) -> T {
+        self.item
+    }
+    fn take_owned(&self) -> [Option<Rc<Self>>; 2] {
+        let mut links = self.links.borrow_mut();
+        [links.child.take(), links.next.take()]
+    }
+}
+
+/// Refers to an element pushed into a `FibonacciHeap`, for `decrease_key`.
+pub type Handle<T> = queue::Handle<Node<T>>;
+
+/// A list of heap-ordered trees that is only tidied up when the top element leaves.
+///
+/// `push` and `meld` just add to the root list in O(1). `pop` merges roots of equal degree until
+/// every degree is distinct, for O(log n) amortized. `decrease_key` cuts the element out to the
+/// root list and cuts any ancestor that has now lost two children, for O(1) amortized. Handles
+/// keep working across `meld`.
+pub struct FibonacciHeap<T, C = MaxOrder> {
+    // Root list, linked through `next` from `roots` to `last`
+    roots: Option<Rc<Node<T>>>,
+    last: Weak<Node<T>>,
+    // The root served next
+    top: Option<Rc<Node<T>>>,
+    len: usize,
+    order: C,
+    owner: Rc<HeapOwner>,
+}
+
+impl<T: Ord> FibonacciHeap<T> {
+    /// Serves the largest element first.
+    pub fn new() -> Self {
+        Self::with_order(MaxOrder)
+    }
+}
+
+impl<T: Ord> FibonacciHeap<T, MinOrder> {
+    /// Serves the smallest element first.
+    pub fn new_min() -> Self {
+        Self::with_order(Reversed(MaxOrder))
+    }
+}
+
+impl<T, C: Compare<T>> FibonacciHeap<T, C> {
+    pub fn with_order(order: C) -> Self {
+        FibonacciHeap { roots: None, last: Weak::new(), top: None, len: 0, order, owner: HeapOwner::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn peek(&self) -> Option<&T> {
+        self.top.as_ref().map(|top| &top.item)
+    }
+    pub fn push(&mut self, item: T) -> Handle<T> {
+        let slot = Rc::new(RefCell::new(Weak::new()));
+        self.push_root(Node::new(item, slot.clone()));
+        self.len += 1;
+        Handle::new(slot, &self.owner)
+    }
+    pub fn pop(&mut self) -> Option<T> {
+        let top = self.top.take()?;
+        self.unlink(&top);
+        let mut next = top.links.borrow_mut().child.take();
+        while let Some(child) = next {
+            next = child.links.borrow_mut().next.take();
+            child.links.borrow_mut().prev = Weak::new();
+            self.push_root(child);
+        }
+        self.consolidate();
+        self.len -= 1;
+        Some(queue::into_item(top))
+    }
+    /// Moves every element of `other` into this heap in O(1).
+    pub fn meld(&mut self, mut other: Self) {
+        other.owner.meld_into(&self.owner);
+        self.len += mem::take(&mut other.len);
+        let last = mem::take(&mut other.last);
+        let Some(head) = other.roots.take() else {
+            return;
+        };
+        match self.last.upgrade() {
+            Some(tail) => {
+                head.links.borrow_mut().prev = Rc::downgrade(&tail);
+                tail.links.borrow_mut().next = Some(head);
+            }
+            None => self.roots = Some(head),
+        }
+        self.last = last;
+        if let Some(top) = other.top.take() {
+            self.offer_top(top);
+        }
+    }
+    /// Whether the element behind `handle` is still queued in this heap.
+    pub fn contains(&self, handle: &Handle<T>) -> bool {
+        handle.is_queued_in(&self.owner)
+    }
+    /// Replaces the element behind `handle` with `item`, returning the old element. Fails if the
+    /// element was popped, the handle came from another heap, or `item` would be served later
+    /// than it.
+    pub fn decrease_key(&mut self, handle: &Handle<T>, item: T) -> crate::Result<T> {
+        let node = handle.resolve(&self.owner)?;
+        if self.order.compare(&item, &node.item) == Ordering::Less {
+            return Err(Error::IllegalArguments("decrease_key would serve the element later".to_string()));
+        }
+        // The replacement takes over the node's place; its children reach it through the slot
+        let replacement = Node::new(item, node.slot.clone());
+        let links = mem::replace(&mut *node.links.borrow_mut(), Links::new());
+        match links.prev.upgrade() {
+            Some(prev) => prev.links.borrow_mut().next = Some(replacement.clone()),
+            None => self.set_head(links.parent.as_ref(), Some(replacement.clone())),
+        }
+        match &links.next {
+            Some(next) => next.links.borrow_mut().prev = Rc::downgrade(&replacement),
+            None if links.parent.is_none() => self.last = Rc::downgrade(&replacement),
+            None => {}
+        }
+        *replacement.links.borrow_mut() = links;
+        if self.top.as_ref().is_some_and(|top| Rc::ptr_eq(top, &node)) {
+            self.top = Some(replacement.clone());
+        }
+
+        match replacement.parent() {
+            Some(parent) => {
+                if self.order.compare(&replacement.item, &parent.item) == Ordering::Greater {
+                    self.cut(&replacement, &parent);
+                    self.cascading_cut(parent);
+                }
+            }
+            None => self.offer_top(replacement),
+        }
+        Ok(queue::into_item(node))
+    }
+
+    // Appends a detached node to the root list.
+    fn push_root(&mut self, node: Rc<Node<T>>) {
+        {
+            let mut links = node.links.borrow_mut();
+            links.parent = None;
+            links.marked = false;
+            links.next = None;
+            links.prev = self.last.clone();
+        }
+        match self.last.upgrade() {
+            Some(tail) => tail.links.borrow_mut().next = Some(node.clone()),
+            None => self.roots = Some(node.clone()),
+        }
+        self.last = Rc::downgrade(&node);
+        self.offer_top(node);
+    }
+    fn offer_top(&mut self, root: Rc<Node<T>>) {
+        let serves_first = match &self.top {
+            Some(top) => self.order.compare(&root.item, &top.item) == Ordering::Greater,
+            None => true,
+        };
+        if serves_first {
+            self.top = Some(root);
+        }
+    }
+    // Takes a node out of the root list or its parent's child list, keeping its subtree.
+    fn unlink(&mut self, node: &Rc<Node<T>>) {
+        let (prev, next, parent) = {
+            let mut links = node.links.borrow_mut();
+            (mem::take(&mut links.prev).upgrade(), links.next.take(), links.parent.clone())
+        };
+        match &next {
+            Some(next) => next.links.borrow_mut().prev = prev.as_ref().map(Rc::downgrade).unwrap_or_default(),
+            None if parent.is_none() => self.last = prev.as_ref().map(Rc::downgrade).unwrap_or_default(),
+            None => {}
+        }
+        match prev {
+            Some(prev) => prev.links.borrow_mut().next = next,
+            None => self.set_head(parent.as_ref(), next),
+        }
+    }
+    // Points the root list, or the child list of the node in `parent`, at a new head.
+    fn set_head(&mut self, parent: Option<&Slot<Node<T>>>, head: Option<Rc<Node<T>>>) {
+        match parent {
+            Some(slot) => {
+                let parent = slot.borrow().upgrade().expect("A parent slot points at a queued node");
+                parent.links.borrow_mut().child = head;
+            }
+            None => self.roots = head,
+        }
+    }
+    // Merges roots of equal degree until all degrees differ, then finds the new top.
+    fn consolidate(&mut self) {
+        let mut by_degree: Vec<Option<Rc<Node<T>>>> = Vec::new();
+        let mut next = self.roots.take();
+        self.last = Weak::new();
+        while let Some(mut root) = next {
+            next = root.links.borrow_mut().next.take();
+            root.links.borrow_mut().prev = Weak::new();
+            loop {
+                let degree = root.links.borrow().degree;
+                if by_degree.len() <= degree {
+                    by_degree.resize(degree + 1, None);
+                }
+                match by_degree[degree].take() {
+                    Some(other) => root = self.link(root, other),
+                    None => {
+                        by_degree[degree] = Some(root);
+                        break;
+                    }
+                }
+            }
+        }
+        self.top = None;
+        for root in by_degree.into_iter().flatten() {
+            self.push_root(root);
+        }
+    }
+    // Makes the loser of two detached roots a child of the winner, which it returns.
+    fn link(&self, a: Rc<Node<T>>, b: Rc<Node<T>>) -> Rc<Node<T>> {
+        let (winner, loser) = if self.order.compare(&b.item, &a.item) == Ordering::Greater { (b, a) } else { (a, b) };
+        let mut winner_links = winner.links.borrow_mut();
+        {
+            let mut loser_links = loser.links.borrow_mut();
+            if let Some(child) = &winner_links.child {
+                child.links.borrow_mut().prev = Rc::downgrade(&loser);
+            }
+            loser_links.parent = Some(winner.slot.clone());
+            loser_links.marked = false;
+            loser_links.next = winner_links.child.take();
+        }
+        winner_links.child = Some(loser);
+        winner_links.degree += 1;
+        drop(winner_links);
+        winner
+    }
+    // Moves `node` from `parent`'s children to the root list.
+    fn cut(&mut self, node: &Rc<Node<T>>, parent: &Rc<Node<T>>) {
+        self.unlink(node);
+        parent.links.borrow_mut().degree -= 1;
+        self.push_root(node.clone());
+    }
+    // Marks a node that just lost a child, or cuts it if it had already lost one, and so on up.
+    fn cascading_cut(&mut self, mut node: Rc<Node<T>>) {
+        while let Some(parent) = node.parent() {
+            if !node.links.borrow().marked {
+                node.links.borrow_mut().marked = true;
+                return;
+            }
+            self.cut(&node, &parent);
+            node = parent;
+        }
+    }
+}
+
+impl<T, C: Compare<T>> Heap for FibonacciHeap<T, C> {
+    type T = T;
+
+    fn push(&mut self, item: T) {
+        FibonacciHeap::push(self, item);
+    }
+    fn pop(&mut self) -> Option<T> {
+        FibonacciHeap::pop(self)
+    }
+    fn peek(&self) -> Option<&T> {
+        FibonacciHeap::peek(self)
+    }
+    fn len(&self) -> usize {
+        self.len
+    }
+    fn meld(&mut self, other: Self) {
+        FibonacciHeap::meld(self, other)
+    }
+}
+
+impl<T, C: Compare<T>> AddressableHeap for FibonacciHeap<T, C> {
+    type Handle = Handle<T>;
+
+    fn push_with_handle(&mut self, item: T) -> Handle<T> {
+        FibonacciHeap::push(self, item)
+    }
+    fn contains(&self, handle: &Handle<T>) -> bool {
+        FibonacciHeap::contains(self, handle)
+    }
+    fn decrease_key(&mut self, handle: &Handle<T>, item: T) -> crate::Result<T> {
+        FibonacciHeap::decrease_key(self, handle, item)
+    }
+}
+
+impl<T, C: Compare<T> + Default> Default for FibonacciHeap<T, C> {
+    fn default() -> Self {
+        Self::with_order(C::default())
+    }
+}
+
+impl<T: Debug, C> Debug for FibonacciHeap<T, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FibonacciHeap")
+            .field("len", &self.len)
+            .field("peek", &self.top.as_ref().map(|top| &top.item))
+            .finish()
+    }
+}
+
+impl<T, C> Drop for FibonacciHeap<T, C> {
+    fn drop(&mut self) {
+        self.top = None;
+        queue::drop_nodes(self.roots.take());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+    use crate::queue;
+    use crate::queue::compare::MinOrder;
+    use super::{FibonacciHeap, Node};
+
+    // Checks heap order, parent slots, degrees and list links below `first`, returning the count.
+    fn check_list(first: Option<Rc<Node<u32>>>, parent: Option<&Rc<Node<u32>>>) -> usize {
+        let mut count = 0;
+        let mut siblings = 0;
+        let mut prev: Option<Rc<Node<u32>>> = None;
+        let mut next = first;
+        while let Some(node) = next {
+            let links = node.links.borrow();
+            assert_eq!(links.prev.upgrade().map(|p| Rc::as_ptr(&p)), prev.as_ref().map(Rc::as_ptr));
+            assert_eq!(node.parent().map(|p| Rc::as_ptr(&p)), parent.map(Rc::as_ptr));
+            if let Some(parent) = parent {
+                assert!(parent.item <= node.item);
+            }
+            count += 1 + check_list(links.child.clone(), Some(&node));
+            siblings += 1;
+            next = links.next.clone();
+            drop(links);
+            prev = Some(node);
+        }
+        if let Some(parent) = parent {
+            assert_eq!(parent.links.borrow().degree, siblings);
+        }
+        count
+    }
+
+    fn check(heap: &FibonacciHeap<u32, MinOrder>) {
+        assert_eq!(check_list(heap.roots.clone(), None), heap.len());
+        assert_eq!(heap.last.upgrade().is_some(), heap.roots.is_some());
+    }
+
+    #[test]
+    fn test_push_pop() {
+        let mut heap = FibonacciHeap::new();
+        assert_eq!(heap.pop(), None);
+        for x in [5, 1, 8, 3, 9, 2] {
+            heap.push(x);
+        }
+        assert_eq!(heap.peek(), Some(&9));
+        let popped: Vec<i32> = std::iter::from_fn(|| heap.pop()).collect();
+        assert_eq!(popped, vec![9, 8, 5, 3, 2, 1]);
+        assert!(heap.is_empty());
+    }
+
+    #[test]
+    fn test_meld_keeps_handles() {
+        queue::test::check_meld_keeps_handles(FibonacciHeap::new_min, check);
+    }
+
+    #[test]
+    fn test_handle_from_another_heap() {
+        queue::test::check_handle_from_another_heap(FibonacciHeap::new_min);
+    }
+
+    #[test]
+    fn test_random_operations_match_sorted() {
+        queue::test::check_random_operations_match_sorted(FibonacciHeap::new_min, check);
+    }
+
+    #[test]
+    fn test_drop_large_heap() {
+        queue::test::check_drop_large_heap(FibonacciHeap::new_min);
+    }
+}
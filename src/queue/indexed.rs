@@ -0,0 +1,234 @@
+use std::cmp::Ordering;
+use std::fmt::{Debug, Formatter};
+use std::hash::Hash;
+use std::mem;
+use crate::map::hash_map::HashMap;
+use crate::queue::compare::{Compare, MaxOrder, MinOrder, Reversed};
+
+/// A binary heap of keys with priorities whose priorities can change while queued.
+///
+/// A position map from each key to its heap slot lets `change_priority`, `decrease_key` and
+/// `remove` find a key in O(1) and restore the heap in O(log n). Each key is queued at most once.
+pub struct IndexedPriorityQueue<K: Hash + Eq, P, C = MaxOrder> {
+    heap: Vec<(K, P)>,
+    positions: HashMap<K, usize>,
+    order: C,
+}
+
+impl<K: Hash + Eq + Clone, P: Ord> IndexedPriorityQueue<K, P> {
+    /// Serves the key with the largest priority first.
+    pub fn new() -> Self {
+        Self::with_order(MaxOrder)
+    }
+}
+
+impl<K: Hash + Eq + Clone, P: Ord> IndexedPriorityQueue<K, P, MinOrder> {
+    /// Serves the key with the smallest priority first.
+    pub fn new_min() -> Self {
+        Self::with_order(Reversed(MaxOrder))
+    }
+}
+
+impl<K: Hash + Eq + Clone, P, C: Compare<P>> IndexedPriorityQueue<K, P, C> {
+    pub fn with_order(order: C) -> Self {
+        IndexedPriorityQueue { heap: Vec::new(), positions: HashMap::new(), order }
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.positions.contains_key(key)
+    }
+    pub fn priority_of(&self, key: &K) -> Option<&P> {
+        self.positions.get(key).map(|&i| &self.heap[i].1)
+    }
+    pub fn order(&self) -> &C {
+        &self.order
+    }
+
+    /// Queues `key`, or moves it to `priority` if it is already queued, returning its old priority.
+    pub fn push(&mut self, key: K, priority: P) -> Option<P> {
+        if self.contains_key(&key) {
+            return self.change_priority(&key, priority);
+        }
+        let i = self.heap.len();
+        self.positions.insert(key.clone(), i);
+        self.heap.push((key, priority));
+        self.sift_up(i);
+        None
+    }
+    pub fn peek(&self) -> Option<(&K, &P)> {
+        self.heap.first().map(|(key, priority)| (key, priority))
+    }
+    pub fn pop(&mut self) -> Option<(K, P)> {
+        if self.heap.is_empty() {
+            return None;
+        }
+        Some(self.remove_at(0))
+    }
+    /// Gives a queued `key` a new priority, returning the old one.
+    pub fn change_priority(&mut self, key: &K, priority: P) -> Option<P> {
+        let i = *self.positions.get(key)?;
+        let old = mem::replace(&mut self.heap[i].1, priority);
+        match self.order.compare(&self.heap[i].1, &old) {
+            Ordering::Greater => self.sift_up(i),
+            Ordering::Less => self.sift_down(i),
+            Ordering::Equal => {}
+        }
+        Some(old)
+    }
+    /// Moves a queued `key` to `priority` only if that would serve it sooner, as when Dijkstra
+    /// finds a shorter distance in a min-queue. Returns whether the priority changed.
+    pub fn decrease_key(&mut self, key: &K, priority: P) -> bool {
+        let Some(&i) = self.positions.get(key) else {
+            return false;
+        };
+        if self.order.compare(&priority, &self.heap[i].1) != Ordering::Greater {
+            return false;
+        }
+        self.heap[i].1 = priority;
+        self.sift_up(i);
+        true
+    }
+    /// Takes `key` out of the queue, returning its priority.
+    pub fn remove(&mut self, key: &K) -> Option<P> {
+        let i = *self.positions.get(key)?;
+        Some(self.remove_at(i).1)
+    }
+    pub fn clear(&mut self) {
+        self.heap.clear();
+        self.positions.clear();
+    }
+    /// The queued keys and priorities in heap order, not in the order they would be popped.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &P)> + '_ {
+        self.heap.iter().map(|(key, priority)| (key, priority))
+    }
+
+    fn remove_at(&mut self, i: usize) -> (K, P) {
+        let last = self.heap.len() - 1;
+        self.swap(i, last);
+        let (key, priority) = self.heap.pop().expect("The heap holds the removed entry");
+        self.positions.remove(&key);
+        if i < self.heap.len() {
+            // The entry moved into `i` came from the bottom, but may belong above or below
+            self.sift_up(i);
+            self.sift_down(i);
+        }
+        (key, priority)
+    }
+    fn swap(&mut self, i: usize, j: usize) {
+        self.heap.swap(i, j);
+        *self.positions.get_mut(&self.heap[i].0).expect("Every queued key has a position") = i;
+        *self.positions.get_mut(&self.heap[j].0).expect("Every queued key has a position") = j;
+    }
+    fn sift_up(&mut self, mut i: usize) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if self.order.compare(&self.heap[i].1, &self.heap[parent].1) != Ordering::Greater {
+                break;
+            }
+            self.swap(i, parent);
+            i = parent;
+        }
+    }
+    fn sift_down(&mut self, mut i: usize) {
+        let size = self.heap.len();
+        loop {
+            let mut first = i;
+            for child in [2 * i + 1, 2 * i + 2] {
+                if child < size && self.order.compare(&self.heap[child].1, &self.heap[first].1) == Ordering::Greater {
+                    first = child;
+                }
+            }
+            if first == i {
+                break;
+            }
+            self.swap(i, first);
+            i = first;
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, P, C: Compare<P> + Default> Default for IndexedPriorityQueue<K, P, C> {
+    fn default() -> Self {
+        Self::with_order(C::default())
+    }
+}
+
+impl<K: Hash + Eq + Debug, P: Debug, C> Debug for IndexedPriorityQueue<K, P, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.heap.iter().map(|(key, priority)| (key, priority))).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use super::IndexedPriorityQueue;
+
+    // Checks the heap property and that every position points at its key.
+    fn check(queue: &IndexedPriorityQueue<u32, u32>) {
+        for (i, (key, priority)) in queue.heap.iter().enumerate() {
+            assert_eq!(queue.positions.get(key), Some(&i));
+            if i > 0 {
+                assert!(queue.heap[(i - 1) / 2].1 >= *priority);
+            }
+        }
+        assert_eq!(queue.positions.len(), queue.heap.len());
+    }
+
+    #[test]
+    fn test_operations() {
+        let mut queue = IndexedPriorityQueue::new_min();
+        queue.push("a", 5);
+        queue.push("b", 3);
+        queue.push("c", 8);
+        assert_eq!(queue.peek(), Some((&"b", &3)));
+
+        assert!(queue.decrease_key(&"c", 1));
+        assert!(!queue.decrease_key(&"a", 9));
+        assert_eq!(queue.priority_of(&"a"), Some(&5));
+        assert_eq!(queue.change_priority(&"a", 0), Some(5));
+        assert_eq!(queue.remove(&"b"), Some(3));
+        assert!(!queue.contains_key(&"b"));
+
+        assert_eq!(queue.pop(), Some(("a", 0)));
+        assert_eq!(queue.pop(), Some(("c", 1)));
+        assert_eq!(queue.pop(), None);
+        assert!(!queue.decrease_key(&"a", 0));
+    }
+
+    #[test]
+    fn test_random_operations_match_map() {
+        let mut rng = StdRng::seed_from_u64(44);
+        let mut queue = IndexedPriorityQueue::new();
+        let mut expected: HashMap<u32, u32> = HashMap::new();
+        for _ in 0..5_000 {
+            let key = rng.gen_range(0..200);
+            let priority = rng.gen_range(0..1_000);
+            match rng.gen_range(0..5) {
+                0 | 1 => assert_eq!(queue.push(key, priority), expected.insert(key, priority)),
+                2 => {
+                    let changed = queue.change_priority(&key, priority);
+                    assert_eq!(changed, expected.get_mut(&key).map(|p| std::mem::replace(p, priority)));
+                }
+                3 => assert_eq!(queue.remove(&key), expected.remove(&key)),
+                _ => {
+                    let (key, priority) = queue.pop().unwrap_or_default();
+                    if !expected.is_empty() {
+                        assert_eq!(Some(priority), expected.values().max().copied());
+                        assert_eq!(expected.remove(&key), Some(priority));
+                    }
+                }
+            }
+            assert_eq!(queue.len(), expected.len());
+        }
+        check(&queue);
+    }
+}
@@ -0,0 +1,403 @@
+use std::cmp::Ordering;
+use std::fmt::{Debug, Formatter};
+use std::mem;
+use crate::queue::Heap;
+
+/// A double-ended priority queue: a binary heap whose levels alternate between ordering down
+/// from the smallest element (even levels, starting at the root) and from the largest (odd
+/// levels).
+///
+/// The smallest element is the root and the largest one of its children, so both ends can be
+/// peeked in O(1) and pushed or popped in O(log n).
+#[derive(Clone)]
+pub struct MinMaxHeap<T: Ord> {
+    heap: Vec<T>,
+}
+
+impl<T: Ord> From<Vec<T>> for MinMaxHeap<T> {
+    /// Arranges `heap` in O(n).
+    fn from(heap: Vec<T>) -> Self {
+        let mut heap = MinMaxHeap { heap };
+        for i in (0..heap.heap.len() / 2).rev() {
+            heap.trickle_down(i);
+        }
+        heap
+    }
+}
+
+impl<T: Ord> MinMaxHeap<T> {
+    pub fn new() -> Self {
+        MinMaxHeap { heap: Vec::new() }
+    }
+    pub fn with_capacity(capacity: usize) -> Self {
+        MinMaxHeap { heap: Vec::with_capacity(capacity) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+    pub fn clear(&mut self) {
+        self.heap.clear();
+    }
+    /// The elements in heap order, not sorted.
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.heap.iter()
+    }
+    pub fn into_vec(self) -> Vec<T> {
+        self.heap
+    }
+
+    pub fn push(&mut self, item: T) {
+        self.heap.push(item);
+        self.bubble_up(self.heap.len() - 1);
+    }
+    pub fn peek_min(&self) -> Option<&T> {
+        self.heap.first()
+    }
+    pub fn peek_max(&self) -> Option<&T> {
+        self.max_index().map(|i| &self.heap[i])
+    }
+    pub fn pop_min(&mut self) -> Option<T> {
+        self.remove_at(0)
+    }
+    pub fn pop_max(&mut self) -> Option<T> {
+        self.max_index().and_then(|i| self.remove_at(i))
+    }
+    /// Swaps `item` in for the largest element and returns that, in one O(log n) pass instead
+    /// of a pop and a push. An empty heap just takes `item`.
+    pub fn replace_max(&mut self, item: T) -> Option<T> {
+        let Some(i) = self.max_index() else {
+            self.push(item);
+            return None;
+        };
+        let old = mem::replace(&mut self.heap[i], item);
+        // The largest sits just below the root, so the newcomer may belong at the root instead
+        if i > 0 && self.heap[i] < self.heap[0] {
+            self.heap.swap(i, 0);
+        }
+        self.trickle_down(i);
+        Some(old)
+    }
+    /// Swaps `item` in for the smallest element and returns that. An empty heap just takes `item`.
+    pub fn replace_min(&mut self, item: T) -> Option<T> {
+        if self.heap.is_empty() {
+            self.push(item);
+            return None;
+        }
+        let old = mem::replace(&mut self.heap[0], item);
+        self.trickle_down(0);
+        Some(old)
+    }
+
+    fn max_index(&self) -> Option<usize> {
+        match self.heap.len() {
+            0 => None,
+            1 => Some(0),
+            2 => Some(1),
+            _ => Some(if self.heap[2] > self.heap[1] { 2 } else { 1 }),
+        }
+    }
+    fn remove_at(&mut self, i: usize) -> Option<T> {
+        if i >= self.heap.len() {
+            return None;
+        }
+        let item = self.heap.swap_remove(i);
+        if i < self.heap.len() {
+            self.trickle_down(i);
+        }
+        Some(item)
+    }
+    // Even levels hold minimums, odd levels maximums.
+    fn is_min_level(i: usize) -> bool {
+        (i + 1).ilog2().is_multiple_of(2)
+    }
+    fn bubble_up(&mut self, i: usize) {
+        if i == 0 {
+            return;
+        }
+        let parent = (i - 1) / 2;
+        // An element on the wrong side of its parent belongs among the parent's levels
+        let want = if Self::is_min_level(i) { Ordering::Less } else { Ordering::Greater };
+        if self.heap[i].cmp(&self.heap[parent]) == want.reverse() {
+            self.heap.swap(i, parent);
+            self.bubble_up_by(parent, want.reverse());
+        } else {
+            self.bubble_up_by(i, want);
+        }
+    }
+    // Moves the element at `i` up through the levels of its kind while it compares as `want`
+    // against its grandparent.
+    fn bubble_up_by(&mut self, mut i: usize, want: Ordering) {
+        while i > 2 {
+            let grandparent = ((i - 1) / 2 - 1) / 2;
+            if self.heap[i].cmp(&self.heap[grandparent]) != want {
+                break;
+            }
+            self.heap.swap(i, grandparent);
+            i = grandparent;
+        }
+    }
+    fn trickle_down(&mut self, i: usize) {
+        let want = if Self::is_min_level(i) { Ordering::Less } else { Ordering::Greater };
+        self.trickle_down_by(i, want);
+    }
+    // Moves the element at `i` down through the levels of its kind, swapping with whichever
+    // child or grandchild compares most as `want`.
+    fn trickle_down_by(&mut self, mut i: usize, want: Ordering) {
+        loop {
+            let first_child = 2 * i + 1;
+            let descendants = [first_child, first_child + 1, 2 * first_child + 1, 2 * first_child + 2, 2 * first_child + 3, 2 * first_child + 4];
+            let Some(m) = descendants.into_iter()
+                .filter(|&d| d < self.heap.len())
+                .reduce(|m, d| if self.heap[d].cmp(&self.heap[m]) == want { d } else { m })
+            else {
+                return;
+            };
+            if self.heap[m].cmp(&self.heap[i]) != want {
+                return;
+            }
+            self.heap.swap(i, m);
+            if m <= first_child + 1 {
+                return;
+            }
+            // A grandchild passed its parent on the way, which belongs to the other kind
+            let parent = (m - 1) / 2;
+            if self.heap[m].cmp(&self.heap[parent]) == want.reverse() {
+                self.heap.swap(m, parent);
+            }
+            i = m;
+        }
+    }
+}
+
+impl<T: Ord> Heap for MinMaxHeap<T> {
+    type T = T;
+
+    fn push(&mut self, item: T) {
+        MinMaxHeap::push(self, item)
+    }
+    fn pop(&mut self) -> Option<T> {
+        self.pop_max()
+    }
+    fn peek(&self) -> Option<&T> {
+        self.peek_max()
+    }
+    fn len(&self) -> usize {
+        self.heap.len()
+    }
+    /// O(n + m): the combined elements are arranged again.
+    fn meld(&mut self, other: Self) {
+        let mut heap = mem::take(&mut self.heap);
+        heap.extend(other.heap);
+        *self = MinMaxHeap::from(heap);
+    }
+}
+
+impl<T: Ord> Default for MinMaxHeap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord + Debug> Debug for MinMaxHeap<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.heap.iter()).finish()
+    }
+}
+
+impl<T: Ord> FromIterator<T> for MinMaxHeap<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        MinMaxHeap::from(iter.into_iter().collect::<Vec<T>>())
+    }
+}
+
+impl<T: Ord> Extend<T> for MinMaxHeap<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+/// A `MinMaxHeap` that holds at most `capacity` elements and drops the smallest on overflow.
+#[derive(Clone, Debug)]
+pub struct BoundedMinMaxHeap<T: Ord> {
+    heap: MinMaxHeap<T>,
+    capacity: usize,
+}
+
+impl<T: Ord> BoundedMinMaxHeap<T> {
+    /// The heap grows as elements arrive rather than reserving `capacity` up front, so a large
+    /// bound costs nothing until it is used.
+    pub fn new(capacity: usize) -> Self {
+        BoundedMinMaxHeap { heap: MinMaxHeap::new(), capacity }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+    pub fn is_full(&self) -> bool {
+        self.heap.len() >= self.capacity
+    }
+    pub fn clear(&mut self) {
+        self.heap.clear();
+    }
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.heap.iter()
+    }
+    pub fn into_inner(self) -> MinMaxHeap<T> {
+        self.heap
+    }
+
+    /// Queues `item`, returning whichever element no longer fits: the old minimum, or `item`
+    /// itself if the queue is full and `item` is no larger than everything in it.
+    pub fn push(&mut self, item: T) -> Option<T> {
+        if !self.is_full() {
+            self.heap.push(item);
+            return None;
+        }
+        match self.heap.peek_min() {
+            Some(min) if item > *min => self.heap.replace_min(item),
+            _ => Some(item),
+        }
+    }
+    pub fn peek_min(&self) -> Option<&T> {
+        self.heap.peek_min()
+    }
+    pub fn peek_max(&self) -> Option<&T> {
+        self.heap.peek_max()
+    }
+    pub fn pop_min(&mut self) -> Option<T> {
+        self.heap.pop_min()
+    }
+    pub fn pop_max(&mut self) -> Option<T> {
+        self.heap.pop_max()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use super::{BoundedMinMaxHeap, MinMaxHeap};
+
+    // Checks that every element lies between its min-level and max-level ancestors.
+    fn check(heap: &MinMaxHeap<u32>) {
+        let items = &heap.heap;
+        for i in 1..items.len() {
+            let mut ancestor = (i - 1) / 2;
+            loop {
+                if MinMaxHeap::<u32>::is_min_level(ancestor) {
+                    assert!(items[ancestor] <= items[i], "{i} is below min ancestor {ancestor}");
+                } else {
+                    assert!(items[ancestor] >= items[i], "{i} is above max ancestor {ancestor}");
+                }
+                if ancestor == 0 {
+                    break;
+                }
+                ancestor = (ancestor - 1) / 2;
+            }
+        }
+    }
+
+    #[test]
+    fn test_both_ends() {
+        let mut heap = MinMaxHeap::new();
+        assert_eq!(heap.peek_max(), None);
+        assert_eq!(heap.pop_min(), None);
+        for x in [5, 1, 8, 3, 9, 2, 7] {
+            heap.push(x);
+        }
+        assert_eq!(heap.peek_min(), Some(&1));
+        assert_eq!(heap.peek_max(), Some(&9));
+        assert_eq!(heap.pop_max(), Some(9));
+        assert_eq!(heap.pop_min(), Some(1));
+        assert_eq!(heap.replace_max(4), Some(8));
+        assert_eq!(heap.peek_max(), Some(&7));
+        assert_eq!(heap.replace_max(0), Some(7));
+        assert_eq!(heap.peek_min(), Some(&0));
+        let mut ascending = Vec::new();
+        while let Some(x) = heap.pop_min() {
+            ascending.push(x);
+        }
+        assert_eq!(ascending, vec![0, 2, 3, 4, 5]);
+        assert_eq!(heap.replace_max(3), None);
+        assert_eq!(heap.len(), 1);
+    }
+
+    #[test]
+    fn test_from_vec() {
+        let mut rng = StdRng::seed_from_u64(46);
+        let items: Vec<u32> = (0..1_000).map(|_| rng.gen_range(0..500)).collect();
+        let mut heap = MinMaxHeap::from(items.clone());
+        check(&heap);
+        let mut sorted = items;
+        sorted.sort();
+        let descending: Vec<u32> = std::iter::from_fn(|| heap.pop_max()).collect();
+        assert_eq!(descending, sorted.into_iter().rev().collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn test_random_operations_match_sorted() {
+        let mut rng = StdRng::seed_from_u64(46);
+        let mut heap = MinMaxHeap::new();
+        let mut expected: Vec<u32> = Vec::new();
+        for _ in 0..5_000 {
+            let x = rng.gen_range(0..1_000);
+            match rng.gen_range(0..6) {
+                0 | 1 => {
+                    heap.push(x);
+                    expected.push(x);
+                }
+                2 => assert_eq!(heap.pop_min(), (!expected.is_empty()).then(|| expected.remove(0))),
+                3 => assert_eq!(heap.pop_max(), expected.pop()),
+                4 => {
+                    let replaced = heap.replace_max(x);
+                    assert_eq!(replaced, expected.pop());
+                    expected.push(x);
+                }
+                _ => {
+                    let replaced = heap.replace_min(x);
+                    assert_eq!(replaced, (!expected.is_empty()).then(|| expected.remove(0)));
+                    expected.push(x);
+                }
+            }
+            expected.sort();
+            assert_eq!(heap.peek_min(), expected.first());
+            assert_eq!(heap.peek_max(), expected.last());
+        }
+        check(&heap);
+    }
+
+    #[test]
+    fn test_bounded_drops_minimum() {
+        let mut heap = BoundedMinMaxHeap::new(3);
+        assert_eq!(heap.push(5), None);
+        assert_eq!(heap.push(1), None);
+        assert_eq!(heap.push(8), None);
+        assert!(heap.is_full());
+        assert_eq!(heap.push(3), Some(1));
+        assert_eq!(heap.push(2), Some(2));
+        assert_eq!(heap.push(9), Some(3));
+        assert_eq!(heap.len(), 3);
+        assert_eq!(heap.pop_max(), Some(9));
+        assert_eq!(heap.pop_min(), Some(5));
+
+        let mut empty = BoundedMinMaxHeap::new(0);
+        assert_eq!(empty.push(1), Some(1));
+        assert!(empty.is_empty());
+
+        let mut unbounded = BoundedMinMaxHeap::new(usize::MAX);
+        assert_eq!(unbounded.push(1), None);
+        assert_eq!(unbounded.len(), 1);
+    }
+}
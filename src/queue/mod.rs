@@ -1,23 +1,165 @@
-use rand::distributions::Uniform;
+use std::cell::RefCell;
+use std::cmp::{max, min, Ord, Ordering};
+use std::fmt::{Debug, Display, Formatter};
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::rc::{Rc, Weak};
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use crate::queue::compare::{Compare, FnOrder, KeyOrder, MaxOrder, MinOrder, Reversed};
+use crate::Error;
 
-use rand::{thread_rng, Rng};
-use std::cmp::{max, min, Ord};
-use std::fmt::Debug;
-use std::fmt::Display;
-use std::time::Instant;
+pub mod compare;
+pub mod concurrent;
+pub mod fibonacci;
+pub mod indexed;
+pub mod min_max;
+pub mod pairing;
+pub mod top_k;
 
-pub struct PriorityQueue<T: Ord + Copy + Display + Debug> {
+/// A queue that serves the greatest element under its order first.
+///
+/// Algorithms written against `Heap` can run on whichever implementation suits their workload.
+pub trait Heap {
+    type T;
+
+    fn push(&mut self, item: Self::T);
+    fn pop(&mut self) -> Option<Self::T>;
+    fn peek(&self) -> Option<&Self::T>;
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Moves every element of `other` into this heap, which keeps its own order.
+    fn meld(&mut self, other: Self) where Self: Sized;
+}
+
+/// A heap that hands out a handle for each pushed element, through which the element can later
+/// be moved forward in the queue.
+pub trait AddressableHeap: Heap {
+    type Handle;
+
+    fn push_with_handle(&mut self, item: Self::T) -> Self::Handle;
+    /// Whether the element behind `handle` is still queued.
+    fn contains(&self, handle: &Self::Handle) -> bool;
+    /// Replaces the element behind `handle` with `item`, which must be served no later than the
+    /// element it replaces, and returns the replaced element.
+    fn decrease_key(&mut self, handle: &Self::Handle, item: Self::T) -> crate::Result<Self::T>;
+}
+
+// Identifies the heap an `AddressableHeap` handle was issued by. A heap melded into another
+// forwards its owner to the survivor's, so its handles are then owned by the survivor.
+pub(crate) struct HeapOwner {
+    id: u64,
+    melded_into: RefCell<Option<Rc<HeapOwner>>>,
+}
+
+impl HeapOwner {
+    pub(crate) fn new() -> Rc<Self> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let id = NEXT_ID.fetch_add(1, AtomicOrdering::Relaxed);
+        Rc::new(HeapOwner { id, melded_into: RefCell::new(None) })
+    }
+    // The id of the live heap now holding this owner's elements. Every owner passed on the way
+    // is repointed straight at that heap's, so later lookups take a single step.
+    pub(crate) fn id(self: &Rc<Self>) -> u64 {
+        let mut survivor = self.clone();
+        loop {
+            let next = survivor.melded_into.borrow().clone();
+            match next {
+                Some(next) => survivor = next,
+                None => break,
+            }
+        }
+        let mut owner = self.clone();
+        while !Rc::ptr_eq(&owner, &survivor) {
+            owner = owner.melded_into.replace(Some(survivor.clone())).expect("Owners short of the survivor were melded");
+        }
+        survivor.id
+    }
+    pub(crate) fn meld_into(&self, survivor: &Rc<HeapOwner>) {
+        *self.melded_into.borrow_mut() = Some(survivor.clone());
+    }
+}
+
+// Points at the node currently holding an element of a `PairingHeap` or `FibonacciHeap`. Handles
+// and the node share it, so `decrease_key` can swap in a replacement node without invalidating
+// any handle.
+pub(crate) type Slot<N> = Rc<RefCell<Weak<N>>>;
+
+// A node of a heap whose nodes own each other through `Rc`s, which `Handle` can refer to.
+pub(crate) trait HeapNode: Sized {
+    type Item;
+    fn into_item(self) -> Self::Item;
+    // Detaches the nodes this one owns: the head of its children and its next neighbour.
+    fn take_owned(&self) -> [Option<Rc<Self>>; 2];
+}
+
+/// Refers to an element pushed into a `PairingHeap` or `FibonacciHeap`, for `decrease_key`. It
+/// stops resolving once the element is popped, and only resolves in the heap that issued it or a
+/// heap that heap was melded into.
+pub struct Handle<N> {
+    slot: Slot<N>,
+    owner: Rc<HeapOwner>,
+}
+
+impl<N> Clone for Handle<N> {
+    fn clone(&self) -> Self {
+        Handle { slot: self.slot.clone(), owner: self.owner.clone() }
+    }
+}
+
+impl<N> Handle<N> {
+    pub(crate) fn new(slot: Slot<N>, owner: &Rc<HeapOwner>) -> Self {
+        Handle { slot, owner: owner.clone() }
+    }
+    pub(crate) fn is_queued_in(&self, owner: &Rc<HeapOwner>) -> bool {
+        self.owner.id() == owner.id() && self.slot.borrow().strong_count() > 0
+    }
+    // The node holding the element, if it is queued in the heap `owner` belongs to.
+    pub(crate) fn resolve(&self, owner: &Rc<HeapOwner>) -> crate::Result<Rc<N>> {
+        if self.owner.id() != owner.id() {
+            return Err(Error::IllegalArguments("The handle was issued by another heap".to_string()));
+        }
+        self.slot.borrow().upgrade()
+            .ok_or_else(|| Error::IllegalArguments("The handle's element is no longer queued".to_string()))
+    }
+}
+
+// Handles only hold weak references, so a node out of the heap has a single owner.
+pub(crate) fn into_item<N: HeapNode>(node: Rc<N>) -> N::Item {
+    match Rc::try_unwrap(node) {
+        Ok(node) => node.into_item(),
+        Err(_) => unreachable!("A node out of the heap has no other owner"),
+    }
+}
+
+// Child and neighbour lists can be as long as the heap, so nodes are unlinked one at a time
+// instead of letting each drop recurse into the next.
+pub(crate) fn drop_nodes<N: HeapNode>(first: Option<Rc<N>>) {
+    let mut stack: Vec<Rc<N>> = first.into_iter().collect();
+    while let Some(node) = stack.pop() {
+        stack.extend(node.take_owned().into_iter().flatten());
+    }
+}
+
+/// A binary heap that serves the greatest element first under its order `C`, which defaults to
+/// `Ord` (a max-heap).
+pub struct PriorityQueue<T, C = MaxOrder> {
     heap: Vec<T>,
+    order: C,
 }
-impl<T: Ord + Copy + Display + Debug> From<Vec<T>> for PriorityQueue<T> {
+impl<T: Ord> From<Vec<T>> for PriorityQueue<T> {
     fn from(value: Vec<T>) -> Self {
-        PriorityQueue { heap: value }
+        Self::from_vec_with_order(value, MaxOrder)
     }
 }
-impl<T: Ord + Copy + Display + Debug> Display for PriorityQueue<T> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl<T: Display, C> Display for PriorityQueue<T, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         // let p = 0;
         let size = self.heap.len();
+        if size == 0 {
+            return Ok(());
+        }
         let max_level = max(1, (size as f64).log2().floor() as usize);
         let mut display_string = String::new();
         for l in 0..=max_level {
@@ -40,58 +182,329 @@ impl<T: Ord + Copy + Display + Debug> Display for PriorityQueue<T> {
         f.write_str(display_string.as_str())
     }
 }
-impl<T: Ord + Copy + Display + Debug> PriorityQueue<T> {
+impl<T: Debug, C> Debug for PriorityQueue<T, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PriorityQueue").field("heap", &self.heap).finish()
+    }
+}
+impl<T: Clone, C: Clone> Clone for PriorityQueue<T, C> {
+    fn clone(&self) -> Self {
+        PriorityQueue { heap: self.heap.clone(), order: self.order.clone() }
+    }
+}
+impl<T, C: Compare<T> + Default> Default for PriorityQueue<T, C> {
+    fn default() -> Self {
+        Self::with_order(C::default())
+    }
+}
+impl<T: Ord> PriorityQueue<T> {
+    /// A max-heap: the largest element comes out first.
     pub fn new() -> Self {
-        PriorityQueue { heap: Vec::new() }
+        Self::with_order(MaxOrder)
+    }
+    pub fn with_capacity(capacity: usize) -> Self {
+        PriorityQueue { heap: Vec::with_capacity(capacity), order: MaxOrder }
+    }
+}
+impl<T: Ord> PriorityQueue<T, MinOrder> {
+    /// A min-heap: the smallest element comes out first.
+    pub fn new_min() -> Self {
+        Self::with_order(Reversed(MaxOrder))
+    }
+}
+impl<T, F: Fn(&T, &T) -> Ordering> PriorityQueue<T, FnOrder<F>> {
+    /// Serves first the element `compare` finds greatest.
+    pub fn with_comparator(compare: F) -> Self {
+        Self::with_order(FnOrder(compare))
+    }
+}
+impl<T, K: Ord, F: Fn(&T) -> K> PriorityQueue<T, KeyOrder<F, K>> {
+    /// Serves the element with the largest key first.
+    pub fn by_key(key: F) -> Self {
+        Self::with_order(KeyOrder::new(key))
+    }
+}
+impl<T, K: Ord, F: Fn(&T) -> K> PriorityQueue<T, Reversed<KeyOrder<F, K>>> {
+    /// Serves the element with the smallest key first.
+    pub fn by_key_min(key: F) -> Self {
+        Self::with_order(Reversed(KeyOrder::new(key)))
+    }
+}
+impl<T, C: Compare<T>> PriorityQueue<T, C> {
+    pub fn with_order(order: C) -> Self {
+        PriorityQueue { heap: Vec::new(), order }
+    }
+    /// Takes over `heap` and arranges it under `order` in O(n).
+    pub fn from_vec_with_order(heap: Vec<T>, order: C) -> Self {
+        let mut queue = PriorityQueue { heap, order };
+        queue.heapify();
+        queue
     }
 
     pub fn push(&mut self, item: T) {
         self.heap.push(item);
-        self.heapify();
+        self.sift_up(self.heap.len() - 1);
     }
     pub fn append(&mut self, items: &mut Vec<T>) {
         self.heap.append(items);
         self.heapify();
     }
     pub fn pop(&mut self) -> Option<T> {
-        let value = self.heap.drain(0..=0).last();
-        value
+        if self.heap.is_empty() {
+            return None;
+        }
+        // Move the last element into the root and let it sink back into place
+        let value = self.heap.swap_remove(0);
+        self.sift_down(0);
+        Some(value)
+    }
+    pub fn peek(&self) -> Option<&T> {
+        self.heap.first()
+    }
+    pub fn order(&self) -> &C {
+        &self.order
     }
-    pub fn peek(&self) -> T {
-        self.heap[0]
+    /// A guard for changing the top element in place. If it was borrowed mutably, it sinks back
+    /// into place when the guard is dropped, in O(log n).
+    pub fn peek_mut(&mut self) -> Option<PeekMut<'_, T, C>> {
+        if self.heap.is_empty() {
+            return None;
+        }
+        Some(PeekMut { queue: self, changed: false })
+    }
+    /// Pushes `item` and pops the top in one O(log n) pass. If `item` would be served first it
+    /// comes straight back without touching the heap.
+    pub fn push_pop(&mut self, item: T) -> T {
+        match self.heap.first() {
+            Some(top) if self.order.compare(&item, top) == Ordering::Less => {
+                let top = mem::replace(&mut self.heap[0], item);
+                self.sift_down(0);
+                top
+            }
+            _ => item,
+        }
+    }
+    /// Pops the top and pushes `item` in one O(log n) pass, returning the old top. An empty queue
+    /// just takes `item`.
+    pub fn replace_top(&mut self, item: T) -> Option<T> {
+        if self.heap.is_empty() {
+            self.heap.push(item);
+            return None;
+        }
+        let top = mem::replace(&mut self.heap[0], item);
+        self.sift_down(0);
+        Some(top)
     }
     pub fn size(&self) -> usize {
         self.heap.len()
     }
-    pub fn heapify(&mut self) {
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+    pub fn clear(&mut self) {
+        self.heap.clear();
+    }
+    /// The elements in heap order, not in the order they would be popped.
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.heap.iter()
+    }
+    /// Takes out every element in heap order, leaving the queue empty.
+    pub fn drain(&mut self) -> std::vec::Drain<'_, T> {
+        self.heap.drain(..)
+    }
+    /// Pops the elements in the order they are served. Elements left when the iterator is
+    /// dropped are removed as well.
+    pub fn drain_sorted(&mut self) -> DrainSorted<'_, T, C> {
+        DrainSorted { queue: self }
+    }
+    /// Keeps only the elements `keep` accepts, rebuilding the heap in O(n) if any were dropped.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, keep: F) {
         let size = self.heap.len();
-        let max_parent = if size % 2 == 0 {
-            (size - 2) / 2
-        } else {
-            (size - 1) / 2
-        };
-        for i in (0..=max_parent).rev() {
-            // println!("i: {i}");
+        self.heap.retain(keep);
+        if self.heap.len() < size {
+            self.heapify();
+        }
+    }
+    /// The elements in ascending order, the one served first last, sorted in place in O(n log n).
+    pub fn into_sorted_vec(mut self) -> Vec<T> {
+        for end in (1..self.heap.len()).rev() {
+            self.heap.swap(0, end);
+            self.sift_down_within(0, end);
+        }
+        self.heap
+    }
+    /// The elements in heap order.
+    pub fn into_vec(self) -> Vec<T> {
+        self.heap
+    }
+    /// Restores the heap property over the whole vector in O(n), sinking every parent from the
+    /// last one up to the root.
+    pub fn heapify(&mut self) {
+        for i in (0..self.heap.len() / 2).rev() {
+            self.sift_down(i);
+        }
+    }
+
+    // Moves the element at `i` up until its parent is no smaller.
+    fn sift_up(&mut self, mut i: usize) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if self.order.compare(&self.heap[i], &self.heap[parent]) != Ordering::Greater {
+                break;
+            }
+            self.heap.swap(i, parent);
+            i = parent;
+        }
+    }
+    // Moves the element at `i` down until neither child is larger.
+    fn sift_down(&mut self, i: usize) {
+        self.sift_down_within(i, self.heap.len());
+    }
+    // Like `sift_down`, treating the heap as ending at `size`.
+    fn sift_down_within(&mut self, mut i: usize, size: usize) {
+        loop {
             let mut largest = i;
             let left = (2 * i) + 1;
             let right = (2 * i) + 2;
 
-            if left < size && self.heap[left] > self.heap[largest] {
+            if left < size && self.order.compare(&self.heap[left], &self.heap[largest]) == Ordering::Greater {
                 largest = left;
             }
-            if right < size && self.heap[right] > self.heap[largest] {
+            if right < size && self.order.compare(&self.heap[right], &self.heap[largest]) == Ordering::Greater {
                 largest = right
             }
-            if largest != i {
-                // println!("Swapping [{}]{} with [{}]{}", i, self.heap[i], largest, self.heap[largest]);
-                self.heap.swap(i, largest);
+            if largest == i {
+                break;
+            }
+            self.heap.swap(i, largest);
+            i = largest;
+        }
+    }
+}
+impl<T, C: Compare<T>> Heap for PriorityQueue<T, C> {
+    type T = T;
+
+    fn push(&mut self, item: T) {
+        PriorityQueue::push(self, item)
+    }
+    fn pop(&mut self) -> Option<T> {
+        PriorityQueue::pop(self)
+    }
+    fn peek(&self) -> Option<&T> {
+        PriorityQueue::peek(self)
+    }
+    fn len(&self) -> usize {
+        self.size()
+    }
+    /// O(n + m): a binary heap has to rebuild itself around the other's elements.
+    fn meld(&mut self, mut other: Self) {
+        self.append(&mut other.heap)
+    }
+}
+impl<T, C: Compare<T> + Default> FromIterator<T> for PriorityQueue<T, C> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from_vec_with_order(iter.into_iter().collect(), C::default())
+    }
+}
+impl<T, C: Compare<T>> Extend<T> for PriorityQueue<T, C> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let mut items: Vec<T> = iter.into_iter().collect();
+        // Rebuilding costs O(n + m) against O(m log(n + m)) for pushing one at a time
+        if items.len() > self.heap.len() {
+            self.append(&mut items);
+        } else {
+            self.heap.reserve(items.len());
+            for item in items {
+                self.push(item);
             }
         }
     }
 }
+impl<'a, T: Copy + 'a, C: Compare<T>> Extend<&'a T> for PriorityQueue<T, C> {
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied())
+    }
+}
+impl<T, C> IntoIterator for PriorityQueue<T, C> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    /// Yields the elements in heap order, not in the order they would be popped.
+    fn into_iter(self) -> Self::IntoIter {
+        self.heap.into_iter()
+    }
+}
+impl<'a, T, C> IntoIterator for &'a PriorityQueue<T, C> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
 
+    fn into_iter(self) -> Self::IntoIter {
+        self.heap.iter()
+    }
+}
+
+/// The top element of a `PriorityQueue`, borrowed for changing; see `PriorityQueue::peek_mut`.
+pub struct PeekMut<'a, T, C: Compare<T>> {
+    queue: &'a mut PriorityQueue<T, C>,
+    // Set once the element is borrowed mutably, after which it may have to sink
+    changed: bool,
+}
+impl<T, C: Compare<T>> PeekMut<'_, T, C> {
+    /// Takes the top element out of the queue.
+    pub fn pop(mut this: Self) -> T {
+        this.changed = false;
+        this.queue.pop().expect("A PeekMut is only handed out for a non-empty queue")
+    }
+}
+impl<T, C: Compare<T>> Deref for PeekMut<'_, T, C> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.queue.heap[0]
+    }
+}
+impl<T, C: Compare<T>> DerefMut for PeekMut<'_, T, C> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.changed = true;
+        &mut self.queue.heap[0]
+    }
+}
+impl<T, C: Compare<T>> Drop for PeekMut<'_, T, C> {
+    fn drop(&mut self) {
+        if self.changed {
+            self.queue.sift_down(0);
+        }
+    }
+}
+
+/// Pops from a `PriorityQueue` in serving order; see `PriorityQueue::drain_sorted`.
+pub struct DrainSorted<'a, T, C: Compare<T>> {
+    queue: &'a mut PriorityQueue<T, C>,
+}
+impl<T, C: Compare<T>> Iterator for DrainSorted<'_, T, C> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.queue.pop()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.queue.size(), Some(self.queue.size()))
+    }
+}
+impl<T, C: Compare<T>> ExactSizeIterator for DrainSorted<'_, T, C> {}
+impl<T, C: Compare<T>> Drop for DrainSorted<'_, T, C> {
+    fn drop(&mut self) {
+        self.queue.clear();
+    }
+}
+
+#[cfg(test)]
 pub mod test {
     use super::*;
+    use rand::distributions::Uniform;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::time::Instant;
 
     fn generate_test_vec<T>(min: T, max: T, size: usize) -> Vec<T>
         where
@@ -105,6 +518,110 @@ pub mod test {
         }
         v
     }
+    // Checks every `AddressableHeap` runs from its own tests, on a min-heap built by `new`.
+
+    pub fn check_meld_keeps_handles<H: AddressableHeap<T = u32>>(new: impl Fn() -> H, inspect: impl Fn(&H)) {
+        let mut a = new();
+        let mut b = new();
+        for x in [10, 20, 30] {
+            a.push(x);
+        }
+        let handle = b.push_with_handle(40);
+        b.push(5);
+        a.meld(b);
+        assert_eq!(a.len(), 5);
+        assert!(a.contains(&handle));
+        assert_eq!(a.peek(), Some(&5));
+        assert_eq!(a.pop(), Some(5));
+        assert_eq!(a.decrease_key(&handle, 1).ok(), Some(40));
+        assert_eq!(a.pop(), Some(1));
+        assert!(!a.contains(&handle));
+        assert!(a.decrease_key(&handle, 0).is_err());
+        inspect(&a);
+    }
+
+    pub fn check_handle_from_another_heap<H: AddressableHeap<T = u32>>(new: impl Fn() -> H) {
+        let mut a = new();
+        let mut b = new();
+        let mut c = new();
+        a.push(10);
+        let from_b = b.push_with_handle(20);
+        let from_c = c.push_with_handle(30);
+        assert!(!a.contains(&from_b));
+        assert!(a.decrease_key(&from_b, 1).is_err());
+        assert_eq!(b.peek(), Some(&20));
+
+        // Melding hands ownership on, through any number of melds
+        b.meld(c);
+        a.meld(b);
+        assert!(a.contains(&from_b) && a.contains(&from_c));
+        assert_eq!(a.decrease_key(&from_c, 1).ok(), Some(30));
+        let mut d = new();
+        d.push(0);
+        assert!(!d.contains(&from_b));
+        assert!(d.decrease_key(&from_b, 0).is_err());
+        assert_eq!(a.pop(), Some(1));
+    }
+
+    // `inspect` runs every few hundred steps, for checking a heap's internal invariants.
+    pub fn check_random_operations_match_sorted<H: AddressableHeap<T = u32>>(new: impl Fn() -> H, inspect: impl Fn(&H)) {
+        let mut rng = StdRng::seed_from_u64(45);
+        let mut heap = new();
+        // Each handle with the value it was last given
+        let mut handles = Vec::new();
+        let mut expected: Vec<u32> = Vec::new();
+        for step in 0..10_000 {
+            match rng.gen_range(0..4) {
+                0 | 1 => {
+                    let x = rng.gen_range(0..100_000);
+                    handles.push((heap.push_with_handle(x), x));
+                    expected.push(x);
+                }
+                2 if !handles.is_empty() => {
+                    let i = rng.gen_range(0..handles.len());
+                    let (handle, value) = &mut handles[i];
+                    if heap.contains(handle) {
+                        let to = rng.gen_range(0..100_000);
+                        match heap.decrease_key(handle, to) {
+                            Ok(old) => {
+                                assert!(to <= old);
+                                assert_eq!(old, *value);
+                                let i = expected.iter().position(|&x| x == old).unwrap();
+                                expected[i] = to;
+                                *value = to;
+                            }
+                            Err(_) => assert!(to > *value),
+                        }
+                    }
+                }
+                _ => {
+                    let min = expected.iter().copied().enumerate().min_by_key(|&(_, x)| x);
+                    assert_eq!(heap.pop(), min.map(|(_, x)| x));
+                    if let Some((i, _)) = min {
+                        expected.swap_remove(i);
+                    }
+                }
+            }
+            assert_eq!(heap.len(), expected.len());
+            if step % 500 == 0 {
+                inspect(&heap);
+            }
+        }
+        inspect(&heap);
+        expected.sort();
+        let popped: Vec<u32> = std::iter::from_fn(|| heap.pop()).collect();
+        assert_eq!(popped, expected);
+    }
+
+    // Rising pushes give long lists of siblings, falling ones long chains of children.
+    pub fn check_drop_large_heap<H: AddressableHeap<T = u32>>(new: impl Fn() -> H) {
+        let mut heap = new();
+        for x in (0..100_000).chain((100_000..200_000).rev()) {
+            heap.push(x);
+        }
+        assert_eq!(heap.len(), 200_000);
+    }
+
     #[test]
     pub fn test_pq() {
         let size = 31;
@@ -124,4 +641,240 @@ pub mod test {
         let elapsed = now.elapsed();
         println!("Elapsed: {:.2?}", elapsed);
     }
+
+    #[test]
+    fn test_pop_order_matches_sorted() {
+        let mut rng = StdRng::seed_from_u64(41);
+        for size in [0, 1, 2, 3, 10, 100, 1_000] {
+            let values: Vec<u32> = (0..size).map(|_| rng.gen_range(0..50)).collect();
+            let mut sorted = values.clone();
+            sorted.sort_by(|a, b| b.cmp(a));
+
+            let mut pushed = PriorityQueue::new();
+            for &value in &values {
+                pushed.push(value);
+            }
+            let mut heapified = PriorityQueue::from(values.clone());
+            let mut appended = PriorityQueue::new();
+            appended.append(&mut values.clone());
+
+            for queue in [&mut pushed, &mut heapified, &mut appended] {
+                let popped: Vec<u32> = std::iter::from_fn(|| queue.pop()).collect();
+                assert_eq!(popped, sorted);
+            }
+        }
+    }
+
+    #[test]
+    fn test_interleaved_push_pop() {
+        let mut rng = StdRng::seed_from_u64(41);
+        let mut queue = PriorityQueue::new();
+        let mut expected: Vec<u32> = Vec::new();
+        for _ in 0..5_000 {
+            if rng.gen_bool(0.6) {
+                let value = rng.gen_range(0..1_000);
+                queue.push(value);
+                expected.push(value);
+                expected.sort();
+            } else {
+                assert_eq!(queue.pop(), expected.pop());
+            }
+            assert_eq!(queue.size(), expected.len());
+        }
+    }
+
+    #[test]
+    fn test_peek() {
+        let mut queue = PriorityQueue::new();
+        assert_eq!(queue.peek(), None);
+        queue.push(3);
+        queue.push(7);
+        assert_eq!(queue.peek(), Some(&7));
+        assert_eq!(queue.size(), 2);
+    }
+
+    #[test]
+    fn test_non_copy_elements() {
+        #[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
+        struct Job {
+            priority: u32,
+            steps: Vec<String>,
+        }
+
+        let mut names = PriorityQueue::from(vec![String::from("b"), String::from("c"), String::from("a")]);
+        assert_eq!(names.pop(), Some(String::from("c")));
+        assert_eq!(format!("{}", PriorityQueue::<String>::new()), "");
+
+        let mut jobs = PriorityQueue::new();
+        jobs.push(Job { priority: 1, steps: vec![String::from("fetch")] });
+        jobs.push(Job { priority: 5, steps: Vec::new() });
+        assert_eq!(jobs.peek().map(|job| job.priority), Some(5));
+        assert_eq!(jobs.pop().map(|job| job.priority), Some(5));
+        assert_eq!(jobs.pop().map(|job| job.steps), Some(vec![String::from("fetch")]));
+        assert!(jobs.is_empty());
+    }
+
+    #[test]
+    fn test_min_heap() {
+        let mut rng = StdRng::seed_from_u64(43);
+        let values: Vec<u32> = (0..500).map(|_| rng.gen_range(0..100)).collect();
+        let mut sorted = values.clone();
+        sorted.sort();
+
+        let mut queue = PriorityQueue::new_min();
+        for &value in &values {
+            queue.push(value);
+        }
+        assert_eq!(queue.peek(), sorted.first());
+        assert_eq!(std::iter::from_fn(|| queue.pop()).collect::<Vec<_>>(), sorted);
+    }
+
+    #[test]
+    fn test_comparator_and_key() {
+        struct Task {
+            name: &'static str,
+            deadline: u32,
+        }
+        fn drain<C: compare::Compare<Task>>(mut queue: PriorityQueue<Task, C>) -> Vec<&'static str> {
+            for (name, deadline) in [("write", 30), ("review", 10), ("ship", 50), ("plan", 20)] {
+                queue.push(Task { name, deadline });
+            }
+            std::iter::from_fn(|| queue.pop()).map(|task| task.name).collect()
+        }
+
+        assert_eq!(drain(PriorityQueue::by_key_min(|task: &Task| task.deadline)), vec!["review", "plan", "write", "ship"]);
+        assert_eq!(drain(PriorityQueue::by_key(|task: &Task| task.deadline)), vec!["ship", "write", "plan", "review"]);
+        let alphabetical = PriorityQueue::with_comparator(|a: &Task, b: &Task| b.name.cmp(a.name));
+        assert_eq!(drain(alphabetical), vec!["plan", "review", "ship", "write"]);
+    }
+
+    #[test]
+    fn test_orders_are_zero_sized() {
+        assert_eq!(std::mem::size_of::<PriorityQueue<u32>>(), std::mem::size_of::<Vec<u32>>());
+        assert_eq!(std::mem::size_of::<PriorityQueue<u32, MinOrder>>(), std::mem::size_of::<Vec<u32>>());
+    }
+
+    // Melds a heap of the evens into one of the odds through the trait and drains it.
+    fn meld_and_drain<H: Heap<T = u32>>(new: impl Fn() -> H) -> Vec<u32> {
+        let (mut odds, mut evens) = (new(), new());
+        for x in (0..20).rev() {
+            if x % 2 == 1 { odds.push(x) } else { evens.push(x) }
+        }
+        odds.meld(evens);
+        assert_eq!(odds.len(), 20);
+        assert_eq!(odds.peek(), Some(&19));
+        std::iter::from_fn(|| odds.pop()).collect()
+    }
+
+    #[test]
+    fn test_heap_implementations_agree() {
+        let expected: Vec<u32> = (0..20).rev().collect();
+        assert_eq!(meld_and_drain(PriorityQueue::new), expected);
+        assert_eq!(meld_and_drain(pairing::PairingHeap::new), expected);
+        assert_eq!(meld_and_drain(fibonacci::FibonacciHeap::new), expected);
+        assert_eq!(meld_and_drain(min_max::MinMaxHeap::new), expected);
+    }
+
+    #[test]
+    fn test_iterators_and_conversions() {
+        let mut rng = StdRng::seed_from_u64(48);
+        let items: Vec<u32> = (0..500).map(|_| rng.gen_range(0..1_000)).collect();
+        let mut sorted = items.clone();
+        sorted.sort();
+
+        let queue: PriorityQueue<u32> = items.iter().copied().collect();
+        assert_eq!(queue.iter().count(), 500);
+        assert_eq!((&queue).into_iter().max(), sorted.last());
+        assert_eq!(queue.clone().into_sorted_vec(), sorted);
+        let mut unordered = queue.clone().into_vec();
+        unordered.sort();
+        assert_eq!(unordered, sorted);
+        let min_queue: PriorityQueue<u32, MinOrder> = items.iter().copied().collect();
+        assert_eq!(min_queue.into_sorted_vec(), sorted.iter().rev().copied().collect::<Vec<u32>>());
+
+        // Extending by fewer elements pushes them, by more rebuilds the heap
+        let mut extended = PriorityQueue::with_capacity(500);
+        extended.extend(&items[..400]);
+        extended.extend(items[400..].iter().copied());
+        assert_eq!(extended.drain_sorted().collect::<Vec<u32>>(), sorted.iter().rev().copied().collect::<Vec<u32>>());
+        assert!(extended.is_empty());
+
+        let mut partly = queue.clone();
+        assert_eq!(partly.drain_sorted().take(2).collect::<Vec<u32>>(), vec![sorted[499], sorted[498]]);
+        assert!(partly.is_empty());
+
+        let mut retained = queue.clone();
+        retained.retain(|x| x % 2 == 0);
+        let evens: Vec<u32> = sorted.iter().copied().filter(|x| x % 2 == 0).collect();
+        assert_eq!(retained.into_sorted_vec(), evens);
+
+        let mut drained = queue.clone();
+        assert_eq!(drained.drain().count(), 500);
+        assert_eq!(drained.pop(), None);
+        let mut cleared = queue;
+        cleared.clear();
+        assert!(cleared.is_empty());
+        assert_eq!(cleared.into_iter().count(), 0);
+    }
+
+    #[test]
+    fn test_peek_mut() {
+        let mut queue = PriorityQueue::from(vec![5, 1, 8, 3, 9]);
+        assert!(PriorityQueue::<u32>::new().peek_mut().is_none());
+        {
+            let top = queue.peek_mut().unwrap();
+            assert_eq!(*top, 9);
+        }
+        *queue.peek_mut().unwrap() = 2;
+        assert_eq!(queue.peek(), Some(&8));
+        if let Some(mut top) = queue.peek_mut() {
+            *top += 10;
+        }
+        assert_eq!(queue.peek(), Some(&18));
+        let top = queue.peek_mut().unwrap();
+        assert_eq!(PeekMut::pop(top), 18);
+        assert_eq!(queue.into_sorted_vec(), vec![1, 2, 3, 5]);
+    }
+
+    #[test]
+    fn test_push_pop_and_replace_top() {
+        let mut queue = PriorityQueue::new_min();
+        assert_eq!(queue.replace_top(4), None);
+        assert_eq!(queue.push_pop(1), 1);
+        queue.extend([7, 2, 9]);
+        assert_eq!(queue.push_pop(5), 2);
+        assert_eq!(queue.replace_top(1), Some(4));
+        assert_eq!(queue.drain_sorted().collect::<Vec<u32>>(), vec![1, 5, 7, 9]);
+
+        // Against a pop followed by a push on a reference queue
+        let mut rng = StdRng::seed_from_u64(49);
+        let mut queue = PriorityQueue::new();
+        let mut reference = PriorityQueue::new();
+        for _ in 0..2_000 {
+            let x = rng.gen_range(0..1_000);
+            if rng.gen_bool(0.5) {
+                reference.push(x);
+                assert_eq!(queue.push_pop(x), reference.pop().unwrap());
+            } else {
+                let popped = reference.pop();
+                reference.push(x);
+                assert_eq!(queue.replace_top(x), popped);
+            }
+            assert_eq!(queue.peek(), reference.peek());
+        }
+    }
+
+    #[test]
+    fn test_heap_owner_shortens_melded_chain() {
+        let owners: Vec<Rc<HeapOwner>> = (0..100).map(|_| HeapOwner::new()).collect();
+        for pair in owners.windows(2) {
+            pair[0].meld_into(&pair[1]);
+        }
+        let survivor = owners.last().unwrap();
+        assert_eq!(owners[0].id(), survivor.id);
+        for owner in &owners[..99] {
+            let next = owner.melded_into.borrow().clone().unwrap();
+            assert!(Rc::ptr_eq(&next, survivor));
+        }
+    }
 }
@@ -0,0 +1,305 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::fmt::{Debug, Formatter};
+use std::rc::{Rc, Weak};
+use crate::queue::compare::{Compare, MaxOrder, MinOrder, Reversed};
+use crate::queue::{self, AddressableHeap, Heap, HeapNode, HeapOwner, Slot};
+use crate::Error;
+
+/// Where a `PairingHeap` keeps an element, for its `Handle`s to refer to.
+pub struct Node<T> {
+    item: T,
+    slot: Slot<Node<T>>,
+    links: RefCell<Links<T>>,
+}
+
+struct Links<T> {
+    // Leftmost child
+    child: Option<Rc<Node<T>>>,
+    // Next sibling to the right
+    sibling: Option<Rc<Node<T>>>,
+    // The parent of a leftmost child, the left sibling of any other; dangling for the root
+    prev: Weak<Node<T>>,
+}
+
+impl<T> Node<T> {
+    fn new(item: T, slot: Slot<Node<T>>) -> Rc<Self> {
+        let node = Rc::new(Node {
+            item,
+            slot,
+            links: RefCell::new(Links { child: None, sibling: None, prev: Weak::new() }),
+        });
+        *node.slot.borrow_mut() = Rc::downgrade(&node);
+        node
+    }
+}
+
+impl<T> HeapNode for Node<T> {
+    type Item = T;
+
+    fn into_item(self) -> T {
+        self.item
+    }
+    fn take_owned(&self) -> [Option<Rc<Self>>; 2] {
+        let mut links = self.links.borrow_mut();
+        [links.child.take(), links.sibling.take()]
+    }
+}
+
+/// Refers to an element pushed into a `PairingHeap`, for `decrease_key`.
+pub type Handle<T> = queue::Handle<Node<T>>;
+
+/// A heap-ordered tree whose root is the next element out, and whose children hang off it in a
+/// list.
+///
+/// `push` and `meld` link two trees in O(1); `pop` pairs up the root's children and merges the
+/// pairs back, for O(log n) amortized. `decrease_key` cuts the element's subtree out and links it
+/// back at the root, which is O(1) in practice. Handles keep working across `meld`.
+pub struct PairingHeap<T, C = MaxOrder> {
+    root: Option<Rc<Node<T>>>,
+    len: usize,
+    order: C,
+    owner: Rc<HeapOwner>,
+}
+
+impl<T: Ord> PairingHeap<T> {
+    /// Serves the largest element first.
+    pub fn new() -> Self {
+        Self::with_order(MaxOrder)
+    }
+}
+
+impl<T: Ord> PairingHeap<T, MinOrder> {
+    /// Serves the smallest element first.
+    pub fn new_min() -> Self {
+        Self::with_order(Reversed(MaxOrder))
+    }
+}
+
+impl<T, C: Compare<T>> PairingHeap<T, C> {
+    pub fn with_order(order: C) -> Self {
+        PairingHeap { root: None, len: 0, order, owner: HeapOwner::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn peek(&self) -> Option<&T> {
+        self.root.as_ref().map(|root| &root.item)
+    }
+    pub fn push(&mut self, item: T) -> Handle<T> {
+        let slot = Rc::new(RefCell::new(Weak::new()));
+        let node = Node::new(item, slot.clone());
+        self.root = Some(match self.root.take() {
+            Some(root) => self.link(root, node),
+            None => node,
+        });
+        self.len += 1;
+        Handle::new(slot, &self.owner)
+    }
+    pub fn pop(&mut self) -> Option<T> {
+        let root = self.root.take()?;
+        let mut children = Vec::new();
+        let mut next = root.links.borrow_mut().child.take();
+        while let Some(child) = next {
+            let mut links = child.links.borrow_mut();
+            next = links.sibling.take();
+            links.prev = Weak::new();
+            drop(links);
+            children.push(child);
+        }
+        // Link neighbours pairwise from the left, then fold the pairs together from the right
+        let mut pairs = Vec::with_capacity(children.len() / 2 + 1);
+        let mut children = children.into_iter();
+        while let Some(a) = children.next() {
+            pairs.push(match children.next() {
+                Some(b) => self.link(a, b),
+                None => a,
+            });
+        }
+        self.root = pairs.into_iter().rev().reduce(|merged, tree| self.link(tree, merged));
+        self.len -= 1;
+        Some(queue::into_item(root))
+    }
+    /// Moves every element of `other` into this heap in O(1).
+    pub fn meld(&mut self, mut other: Self) {
+        other.owner.meld_into(&self.owner);
+        self.root = match (self.root.take(), other.root.take()) {
+            (Some(a), Some(b)) => Some(self.link(a, b)),
+            (a, b) => a.or(b),
+        };
+        self.len += other.len;
+        other.len = 0;
+    }
+    /// Whether the element behind `handle` is still queued in this heap.
+    pub fn contains(&self, handle: &Handle<T>) -> bool {
+        handle.is_queued_in(&self.owner)
+    }
+    /// Replaces the element behind `handle` with `item`, returning the old element. Fails if the
+    /// element was popped, the handle came from another heap, or `item` would be served later
+    /// than it.
+    pub fn decrease_key(&mut self, handle: &Handle<T>, item: T) -> crate::Result<T> {
+        let node = handle.resolve(&self.owner)?;
+        if self.order.compare(&item, &node.item) == Ordering::Less {
+            return Err(Error::IllegalArguments("decrease_key would serve the element later".to_string()));
+        }
+        // The replacement adopts the children; only the leftmost one points back at its parent
+        let replacement = Node::new(item, node.slot.clone());
+        let child = node.links.borrow_mut().child.take();
+        if let Some(child) = &child {
+            child.links.borrow_mut().prev = Rc::downgrade(&replacement);
+        }
+        replacement.links.borrow_mut().child = child;
+
+        let root = self.root.take().expect("A queued element implies a root");
+        self.root = Some(if Rc::ptr_eq(&root, &node) {
+            drop(root);
+            replacement
+        } else {
+            Self::cut(&node);
+            self.link(root, replacement)
+        });
+        Ok(queue::into_item(node))
+    }
+
+    // Makes the loser of two roots the leftmost child of the winner, which it returns.
+    fn link(&self, a: Rc<Node<T>>, b: Rc<Node<T>>) -> Rc<Node<T>> {
+        let (winner, loser) = if self.order.compare(&b.item, &a.item) == Ordering::Greater { (b, a) } else { (a, b) };
+        let mut winner_links = winner.links.borrow_mut();
+        let mut loser_links = loser.links.borrow_mut();
+        if let Some(child) = &winner_links.child {
+            child.links.borrow_mut().prev = Rc::downgrade(&loser);
+        }
+        loser_links.sibling = winner_links.child.take();
+        loser_links.prev = Rc::downgrade(&winner);
+        drop(loser_links);
+        winner_links.child = Some(loser);
+        drop(winner_links);
+        winner
+    }
+    // Unhooks a non-root node, with its subtree, from its parent or left sibling.
+    fn cut(node: &Rc<Node<T>>) {
+        let mut links = node.links.borrow_mut();
+        let prev = links.prev.upgrade().expect("A non-root node has a parent or left sibling");
+        let next = links.sibling.take();
+        links.prev = Weak::new();
+        drop(links);
+        if let Some(next) = &next {
+            next.links.borrow_mut().prev = Rc::downgrade(&prev);
+        }
+        let mut prev_links = prev.links.borrow_mut();
+        if prev_links.child.as_ref().is_some_and(|child| Rc::ptr_eq(child, node)) {
+            prev_links.child = next;
+        } else {
+            prev_links.sibling = next;
+        }
+    }
+}
+
+impl<T, C: Compare<T>> Heap for PairingHeap<T, C> {
+    type T = T;
+
+    fn push(&mut self, item: T) {
+        PairingHeap::push(self, item);
+    }
+    fn pop(&mut self) -> Option<T> {
+        PairingHeap::pop(self)
+    }
+    fn peek(&self) -> Option<&T> {
+        PairingHeap::peek(self)
+    }
+    fn len(&self) -> usize {
+        self.len
+    }
+    fn meld(&mut self, other: Self) {
+        PairingHeap::meld(self, other)
+    }
+}
+
+impl<T, C: Compare<T>> AddressableHeap for PairingHeap<T, C> {
+    type Handle = Handle<T>;
+
+    fn push_with_handle(&mut self, item: T) -> Handle<T> {
+        PairingHeap::push(self, item)
+    }
+    fn contains(&self, handle: &Handle<T>) -> bool {
+        PairingHeap::contains(self, handle)
+    }
+    fn decrease_key(&mut self, handle: &Handle<T>, item: T) -> crate::Result<T> {
+        PairingHeap::decrease_key(self, handle, item)
+    }
+}
+
+impl<T, C: Compare<T> + Default> Default for PairingHeap<T, C> {
+    fn default() -> Self {
+        Self::with_order(C::default())
+    }
+}
+
+impl<T: Debug, C> Debug for PairingHeap<T, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PairingHeap")
+            .field("len", &self.len)
+            .field("peek", &self.root.as_ref().map(|root| &root.item))
+            .finish()
+    }
+}
+
+impl<T, C> Drop for PairingHeap<T, C> {
+    fn drop(&mut self) {
+        queue::drop_nodes(self.root.take());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::queue;
+    use super::PairingHeap;
+
+    #[test]
+    fn test_push_pop() {
+        let mut heap = PairingHeap::new();
+        assert_eq!(heap.pop(), None);
+        for x in [5, 1, 8, 3, 9, 2] {
+            heap.push(x);
+        }
+        assert_eq!(heap.peek(), Some(&9));
+        let popped: Vec<i32> = std::iter::from_fn(|| heap.pop()).collect();
+        assert_eq!(popped, vec![9, 8, 5, 3, 2, 1]);
+        assert!(heap.is_empty());
+    }
+
+    #[test]
+    fn test_decrease_key_rejects_later_items() {
+        let mut heap = PairingHeap::new_min();
+        let handle = heap.push(7);
+        heap.push(3);
+        assert!(heap.decrease_key(&handle, 9).is_err());
+        assert_eq!(heap.decrease_key(&handle, 7).ok(), Some(7));
+        assert_eq!(heap.decrease_key(&handle, 2).ok(), Some(7));
+        assert_eq!(heap.peek(), Some(&2));
+    }
+
+    #[test]
+    fn test_meld_keeps_handles() {
+        queue::test::check_meld_keeps_handles(PairingHeap::new_min, |_| {});
+    }
+
+    #[test]
+    fn test_handle_from_another_heap() {
+        queue::test::check_handle_from_another_heap(PairingHeap::new_min);
+    }
+
+    #[test]
+    fn test_random_operations_match_sorted() {
+        queue::test::check_random_operations_match_sorted(PairingHeap::new_min, |_| {});
+    }
+
+    #[test]
+    fn test_drop_large_heap() {
+        queue::test::check_drop_large_heap(PairingHeap::new_min);
+    }
+}
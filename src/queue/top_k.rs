@@ -0,0 +1,280 @@
+//! Collectors that keep only the best `k` items of a stream.
+//!
+//! Both hold their items in a heap that serves the worst kept item first, so a new item only has
+//! to beat that one to get in, and memory stays at `k` items however long the stream runs.
+use std::cmp::Ordering;
+use std::fmt::{Debug, Formatter};
+use std::hash::Hash;
+use crate::queue::compare::{Compare, MaxOrder, MinOrder, Reversed};
+use crate::queue::indexed::IndexedPriorityQueue;
+use crate::queue::PriorityQueue;
+
+/// The `k` greatest items offered under the order `C`: the largest by default, or the smallest
+/// with `TopK::smallest`.
+pub struct TopK<T, C = MaxOrder> {
+    worst_first: PriorityQueue<T, Reversed<C>>,
+    k: usize,
+}
+
+impl<T: Ord> TopK<T> {
+    pub fn new(k: usize) -> Self {
+        Self::with_order(k, MaxOrder)
+    }
+}
+
+impl<T: Ord> TopK<T, MinOrder> {
+    pub fn smallest(k: usize) -> Self {
+        Self::with_order(k, Reversed(MaxOrder))
+    }
+}
+
+impl<T, C: Compare<T>> TopK<T, C> {
+    pub fn with_order(k: usize, order: C) -> Self {
+        TopK { worst_first: PriorityQueue::with_order(Reversed(order)), k }
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+    pub fn len(&self) -> usize {
+        self.worst_first.size()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.worst_first.is_empty()
+    }
+    /// The item a newcomer has to beat once `k` items are kept.
+    pub fn worst(&self) -> Option<&T> {
+        self.worst_first.peek()
+    }
+
+    /// Keeps `item` if it is among the best `k` so far, in O(log k). Returns the item that did
+    /// not make it: the evicted worst, or `item` itself.
+    pub fn offer(&mut self, item: T) -> Option<T> {
+        if self.len() < self.k {
+            self.worst_first.push(item);
+            return None;
+        }
+        let beats_worst = match self.worst_first.peek() {
+            Some(worst) => self.worst_first.order().compare(&item, worst) == Ordering::Less,
+            None => false,
+        };
+        if !beats_worst {
+            return Some(item);
+        }
+        self.worst_first.replace_top(item)
+    }
+    /// Folds in the items kept by `other`, e.g. a partial result from another thread.
+    pub fn merge(&mut self, mut other: Self) {
+        while let Some(item) = other.worst_first.pop() {
+            self.offer(item);
+        }
+    }
+    /// The kept items best first.
+    pub fn into_sorted_vec(mut self) -> Vec<T> {
+        let mut items = Vec::with_capacity(self.len());
+        while let Some(item) = self.worst_first.pop() {
+            items.push(item);
+        }
+        items.reverse();
+        items
+    }
+}
+
+impl<T, C: Compare<T>> Extend<T> for TopK<T, C> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.offer(item);
+        }
+    }
+}
+
+impl<T: Clone, C: Clone> Clone for TopK<T, C> {
+    fn clone(&self) -> Self {
+        TopK { worst_first: self.worst_first.clone(), k: self.k }
+    }
+}
+
+impl<T: Debug, C> Debug for TopK<T, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TopK").field("k", &self.k).field("items", &self.worst_first).finish()
+    }
+}
+
+/// The `k` distinct keys with the greatest scores under the order `C`.
+///
+/// Offering a kept key again raises its score if the new one is better, so a stream can be fed
+/// through a frequency estimator such as a count-min sketch, offering each key with its updated
+/// estimate, to track the heavy hitters.
+pub struct TopKByKey<K: Hash + Eq, S, C = MaxOrder> {
+    worst_first: IndexedPriorityQueue<K, S, Reversed<C>>,
+    k: usize,
+}
+
+impl<K: Hash + Eq + Clone, S: Ord> TopKByKey<K, S> {
+    pub fn new(k: usize) -> Self {
+        Self::with_order(k, MaxOrder)
+    }
+}
+
+impl<K: Hash + Eq + Clone, S: Ord> TopKByKey<K, S, MinOrder> {
+    pub fn smallest(k: usize) -> Self {
+        Self::with_order(k, Reversed(MaxOrder))
+    }
+}
+
+impl<K: Hash + Eq + Clone, S, C: Compare<S>> TopKByKey<K, S, C> {
+    pub fn with_order(k: usize, order: C) -> Self {
+        TopKByKey { worst_first: IndexedPriorityQueue::with_order(Reversed(order)), k }
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+    pub fn len(&self) -> usize {
+        self.worst_first.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.worst_first.is_empty()
+    }
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.worst_first.contains_key(key)
+    }
+    pub fn score_of(&self, key: &K) -> Option<&S> {
+        self.worst_first.priority_of(key)
+    }
+    /// The kept key with the worst score, which a newcomer has to beat once `k` keys are kept.
+    pub fn worst(&self) -> Option<(&K, &S)> {
+        self.worst_first.peek()
+    }
+
+    /// Keeps `key` if its score is among the best `k`, or raises the score of a kept key, in
+    /// O(log k). Returns the evicted key with its score, if any.
+    pub fn offer(&mut self, key: K, score: S) -> Option<(K, S)> {
+        if let Some(kept) = self.worst_first.priority_of(&key) {
+            // Keeping the worst first, a better score is one the queue would serve later
+            if self.worst_first.order().compare(&score, kept) == Ordering::Less {
+                self.worst_first.change_priority(&key, score);
+            }
+            return None;
+        }
+        if self.len() < self.k {
+            self.worst_first.push(key, score);
+            return None;
+        }
+        let beats_worst = match self.worst_first.peek() {
+            Some((_, worst)) => self.worst_first.order().compare(&score, worst) == Ordering::Less,
+            None => false,
+        };
+        if !beats_worst {
+            return Some((key, score));
+        }
+        let evicted = self.worst_first.pop();
+        self.worst_first.push(key, score);
+        evicted
+    }
+    /// Folds in the keys kept by `other`, keeping the better score of keys kept by both.
+    pub fn merge(&mut self, mut other: Self) {
+        while let Some((key, score)) = other.worst_first.pop() {
+            self.offer(key, score);
+        }
+    }
+    /// The kept keys and scores best first.
+    pub fn into_sorted_vec(mut self) -> Vec<(K, S)> {
+        let mut entries = Vec::with_capacity(self.len());
+        while let Some(entry) = self.worst_first.pop() {
+            entries.push(entry);
+        }
+        entries.reverse();
+        entries
+    }
+}
+
+impl<K: Hash + Eq + Debug, S: Debug, C> Debug for TopKByKey<K, S, C> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TopKByKey").field("k", &self.k).field("entries", &self.worst_first).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use super::{TopK, TopKByKey};
+
+    #[test]
+    fn test_top_k() {
+        let mut largest = TopK::new(3);
+        let mut smallest = TopK::smallest(3);
+        for x in [5, 1, 8, 3, 9, 2, 7] {
+            largest.offer(x);
+            smallest.offer(x);
+        }
+        assert_eq!(largest.len(), 3);
+        assert_eq!(largest.worst(), Some(&7));
+        assert_eq!(largest.offer(4), Some(4));
+        assert_eq!(largest.offer(10), Some(7));
+        assert_eq!(largest.into_sorted_vec(), vec![10, 9, 8]);
+        assert_eq!(smallest.into_sorted_vec(), vec![1, 2, 3]);
+
+        let mut none = TopK::new(0);
+        assert_eq!(none.offer(1), Some(1));
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn test_merge_matches_single_pass() {
+        let mut rng = StdRng::seed_from_u64(47);
+        let items: Vec<u32> = (0..10_000).map(|_| rng.gen_range(0..1_000_000)).collect();
+        let mut merged = TopK::new(50);
+        for chunk in items.chunks(1_000) {
+            let mut partial = TopK::new(50);
+            partial.extend(chunk.iter().copied());
+            merged.merge(partial);
+        }
+        let mut sorted = items;
+        sorted.sort_by(|a, b| b.cmp(a));
+        sorted.truncate(50);
+        assert_eq!(merged.into_sorted_vec(), sorted);
+    }
+
+    #[test]
+    fn test_keyed_keeps_distinct_keys() {
+        let mut top = TopKByKey::new(2);
+        assert_eq!(top.offer("a", 5), None);
+        assert_eq!(top.offer("b", 3), None);
+        assert_eq!(top.offer("a", 4), None);
+        assert_eq!(top.score_of(&"a"), Some(&5));
+        assert_eq!(top.offer("c", 1), Some(("c", 1)));
+        assert_eq!(top.offer("c", 6), Some(("b", 3)));
+        assert_eq!(top.offer("a", 7), None);
+        assert_eq!(top.worst(), Some((&"c", &6)));
+
+        let mut other = TopKByKey::new(2);
+        other.offer("d", 9);
+        other.offer("a", 2);
+        top.merge(other);
+        assert_eq!(top.into_sorted_vec(), vec![("d", 9), ("a", 7)]);
+    }
+
+    #[test]
+    fn test_heavy_hitters() {
+        // Exact counts stand in for a frequency estimator: each key is offered with its new count
+        let mut rng = StdRng::seed_from_u64(47);
+        let mut counts: HashMap<u32, u32> = HashMap::new();
+        let mut top = TopKByKey::new(5);
+        for _ in 0..20_000 {
+            // Cubing a uniform draw skews the stream towards small keys
+            let key = (rng.gen_range(0.0f64..1.0).powi(3) * 1_000.0) as u32;
+            let count = counts.entry(key).or_default();
+            *count += 1;
+            top.offer(key, *count);
+        }
+        let mut expected: Vec<(u32, u32)> = counts.into_iter().collect();
+        expected.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        let found = top.into_sorted_vec();
+        let found_counts: Vec<u32> = found.iter().map(|&(_, count)| count).collect();
+        let expected_counts: Vec<u32> = expected.iter().take(5).map(|&(_, count)| count).collect();
+        assert_eq!(found_counts, expected_counts);
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::hash_map::RandomState;
+use std::fmt::{Debug, Formatter};
+use std::hash::{BuildHasher, Hash};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::thread;
+use crate::Error;
+use crate::set::hash_set::HashSet;
+use crate::set::{EnumerableSet, RemovableSet, Set, SharedSet};
+
+// Shards per available core when none are requested
+const SHARDS_PER_CORE: usize = 4;
+
+/// An exact hash set that many threads can read and write at once.
+///
+/// Elements are spread over independently locked shards, so threads touching different shards
+/// never wait on each other. Single-element operations lock one shard; `len`, `snapshot` and the
+/// set operations hold every shard's read lock together and therefore see one consistent state.
+pub struct ConcurrentSet<T: Hash + Eq> {
+    shards: Vec<RwLock<HashSet<T>>>,
+    hasher: RandomState,
+}
+
+impl<T: Hash + Eq> ConcurrentSet<T> {
+    pub fn new() -> Self {
+        let cores = thread::available_parallelism().map_or(1, |cores| cores.get());
+        Self::with_shards_unchecked((cores * SHARDS_PER_CORE).next_power_of_two())
+    }
+    pub fn with_shards(shards: usize) -> crate::Result<Self> {
+        if shards == 0 {
+            return Err(Error::IllegalArguments("A concurrent set needs at least one shard.".to_string()));
+        }
+        Ok(Self::with_shards_unchecked(shards))
+    }
+    fn with_shards_unchecked(shards: usize) -> Self {
+        ConcurrentSet {
+            shards: (0..shards).map(|_| RwLock::new(HashSet::new())).collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+    pub fn len(&self) -> usize {
+        self.read_all().iter().map(|shard| shard.len()).sum()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.read_all().iter().all(|shard| shard.is_empty())
+    }
+
+    /// Adds `value`, returning whether it was absent.
+    pub fn insert(&self, value: T) -> bool {
+        self.write(&value).insert(value)
+    }
+    pub fn contains(&self, value: &T) -> bool {
+        self.read(value).contains(value)
+    }
+    /// Removes `value`, returning whether it was present.
+    pub fn remove(&self, value: &T) -> bool {
+        self.write(value).remove(value)
+    }
+    pub fn clear(&self) {
+        for mut shard in self.write_all() {
+            shard.clear();
+        }
+    }
+
+    /// A copy of every element as of one moment, in no particular order.
+    pub fn snapshot(&self) -> Vec<T> where T: Clone {
+        self.read_all().iter().flat_map(|shard| shard.iter().cloned()).collect()
+    }
+
+    fn shard(&self, value: &T) -> usize {
+        (self.hasher.hash_one(value) % self.shards.len() as u64) as usize
+    }
+    fn read(&self, value: &T) -> RwLockReadGuard<'_, HashSet<T>> {
+        self.shards[self.shard(value)].read().expect("A writer panicked while holding a shard")
+    }
+    fn write(&self, value: &T) -> RwLockWriteGuard<'_, HashSet<T>> {
+        self.shards[self.shard(value)].write().expect("A writer panicked while holding a shard")
+    }
+    // Always locks in shard order, so concurrent callers cannot deadlock.
+    fn read_all(&self) -> Vec<RwLockReadGuard<'_, HashSet<T>>> {
+        self.shards.iter().map(|shard| shard.read().expect("A writer panicked while holding a shard")).collect()
+    }
+    fn write_all(&self) -> Vec<RwLockWriteGuard<'_, HashSet<T>>> {
+        self.shards.iter().map(|shard| shard.write().expect("A writer panicked while holding a shard")).collect()
+    }
+    fn shards_mut(&mut self) -> impl Iterator<Item = &mut HashSet<T>> + '_ {
+        self.shards.iter_mut().map(|shard| shard.get_mut().expect("A writer panicked while holding a shard"))
+    }
+}
+
+impl<T: Hash + Eq + Clone> ConcurrentSet<T> {
+    // A set with the same number of shards holding the elements `keep` accepts.
+    fn filtered<F: Fn(&T) -> bool>(&self, keep: F) -> Self {
+        let filtered = Self::with_shards_unchecked(self.shards.len());
+        for shard in self.read_all() {
+            for value in shard.iter().filter(|value| keep(value)) {
+                filtered.insert(value.clone());
+            }
+        }
+        filtered
+    }
+}
+
+impl<T: Hash + Eq + Clone> Clone for ConcurrentSet<T> {
+    fn clone(&self) -> Self {
+        self.filtered(|_| true)
+    }
+}
+
+impl<T: Hash + Eq> Default for ConcurrentSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Hash + Eq + Clone + Debug> Debug for ConcurrentSet<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.snapshot()).finish()
+    }
+}
+
+impl<T: Hash + Eq> FromIterator<T> for ConcurrentSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let set = ConcurrentSet::new();
+        for value in iter {
+            set.insert(value);
+        }
+        set
+    }
+}
+
+impl<T: Hash + Eq + Clone> Set for ConcurrentSet<T> {
+    type T = T;
+
+    fn insert(&mut self, e: T) {
+        ConcurrentSet::insert(self, e);
+    }
+    fn contains(&self, e: T) -> bool {
+        ConcurrentSet::contains(self, &e)
+    }
+
+    fn equals(&self, other: Self) -> bool {
+        self.len() == other.len() && self.snapshot().into_iter().all(|e| other.contains(&e))
+    }
+
+    fn union(&self, other: Self) -> crate::Result<Self> {
+        let union = self.clone();
+        for e in other.snapshot() {
+            union.insert(e);
+        }
+        Ok(union)
+    }
+
+    fn intersection(&self, other: Self) -> crate::Result<Self> {
+        Ok(self.filtered(|e| other.contains(e)))
+    }
+
+    fn difference(&self, other: Self) -> crate::Result<Self> {
+        Ok(self.filtered(|e| !other.contains(e)))
+    }
+}
+
+impl<T: Hash + Eq + Clone> RemovableSet for ConcurrentSet<T> {
+    fn remove(&mut self, e: T) -> bool {
+        ConcurrentSet::remove(self, &e)
+    }
+    fn clear(&mut self) {
+        for shard in self.shards_mut() {
+            shard.clear();
+        }
+    }
+}
+
+impl<T: Hash + Eq + Clone> EnumerableSet for ConcurrentSet<T> {
+    fn len(&self) -> usize {
+        ConcurrentSet::len(self)
+    }
+    fn iter(&self) -> Box<dyn Iterator<Item = T> + '_> {
+        Box::new(self.snapshot().into_iter())
+    }
+}
+
+impl<T: Hash + Eq + Clone> SharedSet for ConcurrentSet<T> {
+    type T = T;
+
+    fn insert(&self, e: T) -> bool {
+        ConcurrentSet::insert(self, e)
+    }
+    fn contains(&self, e: T) -> bool {
+        ConcurrentSet::contains(self, &e)
+    }
+    fn remove(&self, e: T) -> bool {
+        ConcurrentSet::remove(self, &e)
+    }
+    fn clear(&self) {
+        ConcurrentSet::clear(self)
+    }
+    fn len(&self) -> usize {
+        ConcurrentSet::len(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::RwLock;
+    use std::thread;
+    use crate::set::hash_set::HashSet;
+    use crate::set::conformance::{self, Config};
+    use crate::set::{EnumerableSet, Set, SharedSet};
+    use super::ConcurrentSet;
+
+    // Pipeline code written once against `SharedSet`.
+    fn ingest<S: SharedSet<T = u32> + Sync>(set: &S, threads: u32, per_thread: u32) {
+        thread::scope(|scope| {
+            for t in 0..threads {
+                scope.spawn(move || {
+                    for i in 0..per_thread {
+                        // Half of every thread's values are shared with the next thread
+                        set.insert(t * per_thread / 2 + i);
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn test_single_thread() {
+        let set = ConcurrentSet::with_shards(4).unwrap();
+        assert!(set.insert("a"));
+        assert!(!set.insert("a"));
+        assert!(set.insert("b"));
+        assert!(set.contains(&"a"));
+        assert!(set.remove(&"a"));
+        assert!(!set.remove(&"a"));
+        assert_eq!(set.len(), 1);
+        assert_eq!(set.snapshot(), vec!["b"]);
+        set.clear();
+        assert!(set.is_empty());
+        assert!(ConcurrentSet::<u32>::with_shards(0).is_err());
+    }
+
+    #[test]
+    fn test_concurrent_ingestion() {
+        let set = ConcurrentSet::new();
+        ingest(&set, 8, 1_000);
+        assert_eq!(set.len(), 4_500);
+        assert!((0..4_500).all(|i| set.contains(&i)));
+
+        let locked = RwLock::new(HashSet::new());
+        ingest(&locked, 8, 1_000);
+        assert_eq!(SharedSet::len(&locked), 4_500);
+    }
+
+    #[test]
+    fn test_concurrent_insert_and_remove() {
+        let set: ConcurrentSet<u32> = (0..10_000).collect();
+        thread::scope(|scope| {
+            for t in 0..4 {
+                let set = &set;
+                scope.spawn(move || {
+                    for i in (t..10_000).step_by(4).filter(|i| i % 2 == 0) {
+                        assert!(set.remove(&i));
+                    }
+                });
+            }
+        });
+        assert_eq!(set.len(), 5_000);
+        let mut remaining = set.snapshot();
+        remaining.sort();
+        assert!(remaining.into_iter().eq((1..10_000).step_by(2)));
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a: ConcurrentSet<u32> = (0..6).collect();
+        let b: ConcurrentSet<u32> = (3..9).collect();
+
+        assert!(a.union(b.clone()).unwrap().equals((0..9).collect()));
+        assert!(a.intersection(b.clone()).unwrap().equals((3..6).collect()));
+        assert!(a.difference(b.clone()).unwrap().equals((0..3).collect()));
+        assert!(a.symmetric_difference(b).unwrap().equals([0, 1, 2, 6, 7, 8].into_iter().collect()));
+    }
+
+    #[test]
+    fn test_conformance() {
+        let config = Config::default();
+        conformance::check_removable_set(&config, || ConcurrentSet::with_shards(4).unwrap(), |id| id);
+        conformance::check_enumerable_set(&config, || ConcurrentSet::with_shards(4).unwrap(), |id| id);
+    }
+}
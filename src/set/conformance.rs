@@ -0,0 +1,225 @@
+//! A randomized conformance suite that any `Set` implementation can run from its tests.
+//!
+//! Elements are named by ids: the caller maps each id to a distinct element, and the suite
+//! replays a seeded stream of operations against both the set and a `std::collections::HashSet`
+//! of ids. Exact sets must agree with the reference on every answer. Approximate sets may report
+//! false positives, but their observed rate on absent elements must stay within a few standard
+//! deviations of the rate declared by `error_bounds`, and they may only miss an element after
+//! declaring that false negatives are possible.
+use std::collections::HashSet;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::set::{EnumerableSet, RemovableSet, Set};
+
+// Standard deviations an observed false positive rate may exceed the declared one by
+const SIGMAS: f64 = 4.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    pub seed: u64,
+    // Random operations replayed before the final checks
+    pub operations: usize,
+    // Ids of the elements operations draw from are below this
+    pub universe: u64,
+    // Ids at or above `universe`, never inserted, probed to measure false positives
+    pub probes: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config { seed: 0x5e7, operations: 2_000, universe: 500, probes: 10_000 }
+    }
+}
+
+/// What a run observed while probing elements that were never inserted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Report {
+    pub probes: u64,
+    pub false_positives: u64,
+    pub observed_fpr: f64,
+    pub declared_fpr: f64,
+}
+
+/// Checks `insert`, `contains`, `equals` and the set operations.
+///
+/// `new` must build empty sets that can be combined with each other, and `element` must map
+/// distinct ids to distinct elements. Panics on the first disagreement with the reference.
+pub fn check_set<S, N, E>(config: &Config, new: N, element: E) -> Report
+where
+    S: Set,
+    N: Fn() -> S,
+    E: Fn(u64) -> S::T,
+{
+    Checker { config, new, element }.run(None)
+}
+
+/// Like `check_set`, with `remove` and `clear` mixed into the operations.
+pub fn check_removable_set<S, N, E>(config: &Config, new: N, element: E) -> Report
+where
+    S: RemovableSet,
+    N: Fn() -> S,
+    E: Fn(u64) -> S::T,
+{
+    Checker { config, new, element }.run(Some(Removal { remove: S::remove, clear: S::clear }))
+}
+
+/// Checks `len`, `iter` and the subset relations of an exact set.
+pub fn check_enumerable_set<S, N, E>(config: &Config, new: N, element: E)
+where
+    S: EnumerableSet,
+    S::T: PartialEq,
+    N: Fn() -> S,
+    E: Fn(u64) -> S::T,
+{
+    let checker = Checker { config, new, element };
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut previous: Option<(S, HashSet<u64>)> = None;
+    for round in 0..8 {
+        let (set, reference) = checker.random_set(&mut rng);
+        assert_eq!(set.len(), reference.len(), "round {round}: len");
+        assert_eq!(set.is_empty(), reference.is_empty(), "round {round}: is_empty");
+        let elements: Vec<S::T> = set.iter().collect();
+        assert_eq!(elements.len(), reference.len(), "round {round}: iter yielded a wrong count");
+        for &id in &reference {
+            assert!(elements.contains(&(checker.element)(id)), "round {round}: iter missed {id}");
+        }
+
+        if let Some((other, other_reference)) = &previous {
+            assert_eq!(set.is_subset(other), reference.is_subset(other_reference), "round {round}: is_subset");
+            assert_eq!(set.is_superset(other), reference.is_superset(other_reference), "round {round}: is_superset");
+            assert_eq!(set.is_disjoint(other), reference.is_disjoint(other_reference), "round {round}: is_disjoint");
+        }
+        let subset: HashSet<u64> = reference.iter().copied().filter(|_| rng.gen_bool(0.5)).collect();
+        assert!(checker.build(&subset).is_subset(&set), "round {round}: a subset was not is_subset");
+        assert!(set.is_superset(&checker.build(&subset)), "round {round}: not is_superset of a subset");
+        previous = Some((set, reference));
+    }
+}
+
+// The `RemovableSet` operations, for checkers running on a set that has them.
+struct Removal<S: Set> {
+    remove: fn(&mut S, S::T) -> bool,
+    clear: fn(&mut S),
+}
+
+struct Checker<'a, N, E> {
+    config: &'a Config,
+    new: N,
+    element: E,
+}
+
+impl<S, N, E> Checker<'_, N, E>
+where
+    S: Set,
+    N: Fn() -> S,
+    E: Fn(u64) -> S::T,
+{
+    fn run(&self, removal: Option<Removal<S>>) -> Report {
+        let mut rng = StdRng::seed_from_u64(self.config.seed);
+        let mut set = (self.new)();
+        let mut reference = HashSet::new();
+        for step in 0..self.config.operations {
+            let id = rng.gen_range(0..self.config.universe);
+            match (rng.gen_range(0..10), &removal) {
+                (0..=3, _) => {
+                    set.insert((self.element)(id));
+                    reference.insert(id);
+                }
+                (4..=5, Some(removal)) => {
+                    let removed = (removal.remove)(&mut set, (self.element)(id));
+                    if set.error_bounds().is_exact() {
+                        assert_eq!(removed, reference.contains(&id), "step {step}: remove({id})");
+                    }
+                    reference.remove(&id);
+                }
+                (_, Some(removal)) if rng.gen_ratio(1, 500) => {
+                    (removal.clear)(&mut set);
+                    reference.clear();
+                }
+                _ => {
+                    self.check_member(&set, id, reference.contains(&id), &format!("step {step}"));
+                }
+            }
+        }
+        for id in 0..self.config.universe {
+            self.check_member(&set, id, reference.contains(&id), "after all steps");
+        }
+        let report = self.probe(&set);
+        self.check_operations(&mut rng);
+        report
+    }
+
+    // Returns what the set answered.
+    fn check_member(&self, set: &S, id: u64, expected: bool, context: &str) -> bool {
+        let bounds = set.error_bounds();
+        let actual = set.contains((self.element)(id));
+        if expected && !actual {
+            assert!(bounds.false_negatives, "{context}: {id} was inserted but is reported absent");
+        }
+        if !expected && actual {
+            assert!(bounds.false_positive_rate > 0.0, "{context}: {id} is reported present but was never inserted");
+        }
+        actual
+    }
+
+    fn probe(&self, set: &S) -> Report {
+        let mut false_positives = 0;
+        let ids = self.config.universe..self.config.universe + self.config.probes;
+        for id in ids {
+            if self.check_member(set, id, false, "probing absent elements") {
+                false_positives += 1;
+            }
+        }
+        let probes = self.config.probes;
+        let observed_fpr = false_positives as f64 / probes as f64;
+        let declared_fpr = set.error_bounds().false_positive_rate;
+        let deviation = (declared_fpr * (1.0 - declared_fpr) / probes as f64).sqrt();
+        assert!(
+            observed_fpr <= declared_fpr + SIGMAS * deviation,
+            "observed false positive rate {observed_fpr:.5} exceeds the declared {declared_fpr:.5}",
+        );
+        Report { probes, false_positives, observed_fpr, declared_fpr }
+    }
+
+    fn check_operations(&self, rng: &mut StdRng) {
+        let (a, a_ids) = self.random_set(rng);
+        let (b, b_ids) = self.random_set(rng);
+
+        let union = a.union(b).expect("Sets built alike can be combined");
+        let intersection = a.intersection(self.build(&b_ids)).expect("Sets built alike can be combined");
+        let difference = a.difference(self.build(&b_ids)).expect("Sets built alike can be combined");
+        for id in 0..self.config.universe {
+            let (in_a, in_b) = (a_ids.contains(&id), b_ids.contains(&id));
+            self.check_member(&union, id, in_a || in_b, "union");
+            self.check_member(&intersection, id, in_a && in_b, "intersection");
+            self.check_member(&difference, id, in_a && !in_b, "difference");
+        }
+
+        let mut reversed: Vec<u64> = a_ids.iter().copied().collect();
+        reversed.sort_by(|x, y| y.cmp(x));
+        let mut rebuilt = (self.new)();
+        for id in reversed {
+            rebuilt.insert((self.element)(id));
+        }
+        assert!(a.equals(rebuilt), "a set does not equal itself built in another order");
+        if a.error_bounds().is_exact() {
+            assert_eq!(a.equals(self.build(&b_ids)), a_ids == b_ids, "equals");
+        }
+    }
+
+    fn random_set(&self, rng: &mut StdRng) -> (S, HashSet<u64>) {
+        let density = rng.gen_range(0.0..0.5);
+        let ids: HashSet<u64> = (0..self.config.universe).filter(|_| rng.gen_bool(density)).collect();
+        (self.build(&ids), ids)
+    }
+
+    fn build(&self, ids: &HashSet<u64>) -> S {
+        let mut sorted: Vec<u64> = ids.iter().copied().collect();
+        sorted.sort();
+        let mut set = (self.new)();
+        for id in sorted {
+            set.insert((self.element)(id));
+        }
+        set
+    }
+}
@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Union-find over the dense ids `0..len`, with path compression and union by rank.
+#[derive(Clone, Debug, Default)]
+pub struct DisjointSet {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+    // Only meaningful for roots
+    size: Vec<usize>,
+    components: usize,
+}
+
+impl DisjointSet {
+    pub fn new(len: usize) -> Self {
+        DisjointSet {
+            parent: (0..len).collect(),
+            rank: vec![0; len],
+            size: vec![1; len],
+            components: len,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+    pub fn component_count(&self) -> usize {
+        self.components
+    }
+
+    /// Adds a singleton and returns its id.
+    pub fn make_set(&mut self) -> usize {
+        let id = self.parent.len();
+        self.parent.push(id);
+        self.rank.push(0);
+        self.size.push(1);
+        self.components += 1;
+        id
+    }
+    /// The representative of `x`'s component, flattening the path to it along the way.
+    pub fn find(&mut self, x: usize) -> usize {
+        let root = self.root(x);
+        let mut node = x;
+        while self.parent[node] != root {
+            node = std::mem::replace(&mut self.parent[node], root);
+        }
+        root
+    }
+    /// Like `find`, but leaves the structure untouched.
+    pub fn root(&self, x: usize) -> usize {
+        let mut node = x;
+        while self.parent[node] != node {
+            node = self.parent[node];
+        }
+        node
+    }
+    /// Merges the components of `a` and `b`, returning whether they were separate.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        let (child, root) = if self.rank[a] < self.rank[b] { (a, b) } else { (b, a) };
+        self.parent[child] = root;
+        self.size[root] += self.size[child];
+        if self.rank[child] == self.rank[root] {
+            self.rank[root] += 1;
+        }
+        self.components -= 1;
+        true
+    }
+    pub fn same_set(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+    pub fn component_size(&mut self, x: usize) -> usize {
+        let root = self.find(x);
+        self.size[root]
+    }
+    /// Every id sharing a component with `x`, in ascending order.
+    pub fn members(&self, x: usize) -> Vec<usize> {
+        let root = self.root(x);
+        (0..self.len()).filter(|&y| self.root(y) == root).collect()
+    }
+    /// Every component, each in ascending order, ordered by smallest member.
+    pub fn components(&self) -> Vec<Vec<usize>> {
+        let mut by_root: Vec<Option<usize>> = vec![None; self.len()];
+        let mut components: Vec<Vec<usize>> = Vec::with_capacity(self.components);
+        for x in 0..self.len() {
+            let root = self.root(x);
+            let index = *by_root[root].get_or_insert_with(|| {
+                components.push(Vec::new());
+                components.len() - 1
+            });
+            components[index].push(x);
+        }
+        components
+    }
+}
+
+/// Union-find over arbitrary hashable keys, each mapped to a dense id.
+#[derive(Clone, Debug)]
+pub struct KeyedDisjointSet<K: Hash + Eq> {
+    keys: Vec<K>,
+    ids: HashMap<K, usize>,
+    sets: DisjointSet,
+}
+
+impl<K: Hash + Eq + Clone> KeyedDisjointSet<K> {
+    pub fn new() -> Self {
+        KeyedDisjointSet {
+            keys: Vec::new(),
+            ids: HashMap::new(),
+            sets: DisjointSet::new(0),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+    pub fn component_count(&self) -> usize {
+        self.sets.component_count()
+    }
+    pub fn contains(&self, key: &K) -> bool {
+        self.ids.contains_key(key)
+    }
+
+    /// Adds `key` as a singleton, returning whether it was new.
+    pub fn make_set(&mut self, key: K) -> bool {
+        if self.ids.contains_key(&key) {
+            return false;
+        }
+        self.id_of(key);
+        true
+    }
+    /// The representative key of `key`'s component.
+    pub fn find(&mut self, key: &K) -> Option<&K> {
+        let id = *self.ids.get(key)?;
+        let root = self.sets.find(id);
+        Some(&self.keys[root])
+    }
+    /// Merges the components of `a` and `b`, adding either key if it is new. Returns whether they
+    /// were separate.
+    pub fn union(&mut self, a: K, b: K) -> bool {
+        let (a, b) = (self.id_of(a), self.id_of(b));
+        self.sets.union(a, b)
+    }
+    pub fn same_set(&mut self, a: &K, b: &K) -> bool {
+        match (self.ids.get(a), self.ids.get(b)) {
+            (Some(&a), Some(&b)) => self.sets.same_set(a, b),
+            _ => false,
+        }
+    }
+    pub fn component_size(&mut self, key: &K) -> Option<usize> {
+        let id = *self.ids.get(key)?;
+        Some(self.sets.component_size(id))
+    }
+    /// Every key sharing a component with `key`, in insertion order.
+    pub fn members(&self, key: &K) -> Vec<&K> {
+        match self.ids.get(key) {
+            Some(&id) => self.sets.members(id).into_iter().map(|id| &self.keys[id]).collect(),
+            None => Vec::new(),
+        }
+    }
+    pub fn components(&self) -> Vec<Vec<&K>> {
+        self.sets.components().into_iter()
+            .map(|ids| ids.into_iter().map(|id| &self.keys[id]).collect())
+            .collect()
+    }
+
+    fn id_of(&mut self, key: K) -> usize {
+        if let Some(&id) = self.ids.get(&key) {
+            return id;
+        }
+        let id = self.sets.make_set();
+        self.keys.push(key.clone());
+        self.ids.insert(key, id);
+        id
+    }
+}
+
+impl<K: Hash + Eq + Clone> Default for KeyedDisjointSet<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq + Clone> FromIterator<K> for KeyedDisjointSet<K> {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut sets = KeyedDisjointSet::new();
+        for key in iter {
+            sets.make_set(key);
+        }
+        sets
+    }
+}
+
+/// Union-find whose unions can be undone in reverse order, for offline algorithms that explore
+/// and backtrack.
+///
+/// Path compression would make undo expensive, so only union by rank is used and `find` is
+/// O(log n).
+#[derive(Clone, Debug, Default)]
+pub struct RollbackDisjointSet {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+    size: Vec<usize>,
+    components: usize,
+    // (child root, whether the new root's rank was bumped) for every successful union
+    history: Vec<(usize, bool)>,
+}
+
+impl RollbackDisjointSet {
+    pub fn new(len: usize) -> Self {
+        RollbackDisjointSet {
+            parent: (0..len).collect(),
+            rank: vec![0; len],
+            size: vec![1; len],
+            components: len,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+    pub fn component_count(&self) -> usize {
+        self.components
+    }
+
+    pub fn find(&self, x: usize) -> usize {
+        let mut node = x;
+        while self.parent[node] != node {
+            node = self.parent[node];
+        }
+        node
+    }
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        let (child, root) = if self.rank[a] < self.rank[b] { (a, b) } else { (b, a) };
+        let bumped = self.rank[child] == self.rank[root];
+        self.parent[child] = root;
+        self.size[root] += self.size[child];
+        if bumped {
+            self.rank[root] += 1;
+        }
+        self.components -= 1;
+        self.history.push((child, bumped));
+        true
+    }
+    pub fn same_set(&self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+    pub fn component_size(&self, x: usize) -> usize {
+        self.size[self.find(x)]
+    }
+
+    /// A marker to later `rollback` to.
+    pub fn snapshot(&self) -> usize {
+        self.history.len()
+    }
+    /// Undoes the most recent successful union, returning whether there was one.
+    pub fn undo(&mut self) -> bool {
+        let Some((child, bumped)) = self.history.pop() else {
+            return false;
+        };
+        let root = self.parent[child];
+        self.parent[child] = child;
+        self.size[root] -= self.size[child];
+        if bumped {
+            self.rank[root] -= 1;
+        }
+        self.components += 1;
+        true
+    }
+    /// Undoes every union made since `snapshot` was taken.
+    pub fn rollback(&mut self, snapshot: usize) {
+        while self.history.len() > snapshot {
+            self.undo();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DisjointSet, KeyedDisjointSet, RollbackDisjointSet};
+
+    #[test]
+    fn test_union_find() {
+        let mut sets = DisjointSet::new(6);
+        assert_eq!(sets.component_count(), 6);
+        assert!(sets.union(0, 1));
+        assert!(sets.union(2, 3));
+        assert!(sets.union(1, 3));
+        assert!(!sets.union(0, 2));
+        assert_eq!(sets.component_count(), 3);
+
+        assert!(sets.same_set(0, 3));
+        assert!(!sets.same_set(0, 4));
+        assert_eq!(sets.component_size(2), 4);
+        assert_eq!(sets.members(3), vec![0, 1, 2, 3]);
+        assert_eq!(sets.components(), vec![vec![0, 1, 2, 3], vec![4], vec![5]]);
+
+        let id = sets.make_set();
+        assert_eq!(id, 6);
+        sets.union(5, id);
+        assert_eq!(sets.components(), vec![vec![0, 1, 2, 3], vec![4], vec![5, 6]]);
+    }
+
+    #[test]
+    fn test_path_compression() {
+        let mut sets = DisjointSet::new(100);
+        for x in 1..100 {
+            sets.union(x - 1, x);
+        }
+        let root = sets.find(99);
+        assert!((0..100).all(|x| sets.parent[x] == root || sets.parent[sets.parent[x]] == root));
+        assert!(sets.rank[root] <= 7);
+    }
+
+    #[test]
+    fn test_keyed() {
+        let mut sets: KeyedDisjointSet<&str> = ["a", "b", "c"].into_iter().collect();
+        assert!(sets.union("a", "b"));
+        assert!(sets.union("d", "e"));
+        assert_eq!(sets.len(), 5);
+        assert_eq!(sets.component_count(), 3);
+
+        assert!(sets.same_set(&"a", &"b"));
+        assert!(!sets.same_set(&"a", &"d"));
+        assert!(!sets.same_set(&"a", &"z"));
+        let root = sets.find(&"a").copied();
+        assert_eq!(sets.find(&"b").copied(), root);
+        assert_eq!(sets.find(&"z"), None);
+        assert_eq!(sets.component_size(&"e"), Some(2));
+        assert_eq!(sets.members(&"e"), vec![&"d", &"e"]);
+        assert_eq!(sets.components(), vec![vec![&"a", &"b"], vec![&"c"], vec![&"d", &"e"]]);
+    }
+
+    #[test]
+    fn test_rollback() {
+        let mut sets = RollbackDisjointSet::new(5);
+        sets.union(0, 1);
+        let snapshot = sets.snapshot();
+        sets.union(1, 2);
+        sets.union(3, 4);
+        assert!(!sets.union(0, 2));
+        assert_eq!(sets.component_count(), 2);
+        assert_eq!(sets.component_size(2), 3);
+
+        sets.rollback(snapshot);
+        assert_eq!(sets.component_count(), 4);
+        assert!(sets.same_set(0, 1));
+        assert!(!sets.same_set(1, 2));
+        assert!(!sets.same_set(3, 4));
+        assert_eq!(sets.component_size(0), 2);
+
+        assert!(sets.undo());
+        assert!(!sets.undo());
+        assert_eq!(sets.component_count(), 5);
+    }
+}
@@ -0,0 +1,134 @@
+//! Empirical false positive rates of approximate sets.
+//!
+//! `measure` fills a set with a chosen number of elements, then probes elements that were never
+//! inserted and counts how many the set claims to hold. Comparing that rate with the configured
+//! and estimated ones shows whether the set delivers what it promises; the slot histogram shows
+//! whether its hashing spreads elements evenly.
+use std::fmt::{Display, Formatter};
+use crate::set::ApproximateSet;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measurement {
+    pub inserted: u64,
+    pub probes: u64,
+    pub false_positives: u64,
+    pub observed_fpr: f64,
+    // What the set was configured for
+    pub configured_fpr: f64,
+    // What the set predicts from its fill after the inserts
+    pub estimated_fpr: f64,
+    // Hits per equal-width range of slots over all inserted elements; empty if slots are hidden
+    pub histogram: Vec<u64>,
+    pub slot_count: usize,
+}
+
+impl Measurement {
+    /// Pearson's chi-squared statistic of the histogram against a uniform spread.
+    ///
+    /// For evenly spread slots it stays near `histogram.len() - 1`; far larger values mean the
+    /// hashing favours some slots.
+    pub fn chi_squared(&self) -> f64 {
+        let total: u64 = self.histogram.iter().sum();
+        if total == 0 {
+            return 0.0;
+        }
+        // Buckets may differ by one slot in width, so each expects its share of the slots
+        let buckets = self.histogram.len();
+        self.histogram.iter().enumerate()
+            .map(|(b, &hits)| {
+                let width = bucket_start(b + 1, buckets, self.slot_count) - bucket_start(b, buckets, self.slot_count);
+                let expected = total as f64 * width as f64 / self.slot_count as f64;
+                (hits as f64 - expected).powi(2) / expected
+            })
+            .sum()
+    }
+}
+
+// First slot of bucket `b` when `slots` are split into `buckets` nearly equal ranges.
+fn bucket_start(b: usize, buckets: usize, slots: usize) -> usize {
+    (b as u128 * slots as u128 / buckets as u128) as usize
+}
+
+/// Inserts the elements for ids `0..inserts` into `set`, then probes ids `inserts..inserts + probes`.
+///
+/// `element` must map distinct ids to distinct elements, so every probe is known to be absent.
+/// Slot hits of the inserted elements are grouped into `buckets` ranges.
+pub fn measure<S, E>(set: &mut S, inserts: u64, probes: u64, buckets: usize, element: E) -> Measurement
+where
+    S: ApproximateSet,
+    E: Fn(u64) -> S::T,
+{
+    let slot_count = set.slot_count();
+    let buckets = if slot_count == 0 { 0 } else { buckets.clamp(1, slot_count) };
+    let mut histogram = vec![0; buckets];
+    for id in 0..inserts {
+        if buckets > 0 {
+            for slot in set.slots(element(id)) {
+                histogram[(slot as u128 * buckets as u128 / slot_count as u128) as usize] += 1;
+            }
+        }
+        set.insert(element(id));
+    }
+    let false_positives = (inserts..inserts + probes).filter(|&id| set.contains(element(id))).count() as u64;
+    Measurement {
+        inserted: inserts,
+        probes,
+        false_positives,
+        observed_fpr: if probes == 0 { 0.0 } else { false_positives as f64 / probes as f64 },
+        configured_fpr: set.false_positive_probability(),
+        estimated_fpr: set.estimated_fpr(),
+        histogram,
+        slot_count,
+    }
+}
+
+impl Display for Measurement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "inserted:\t{}\nprobes:\t\t{}\nfalse positives:{}", self.inserted, self.probes, self.false_positives)?;
+        writeln!(f, "observed fpr:\t{:.6}", self.observed_fpr)?;
+        writeln!(f, "configured fpr:\t{:.6}", self.configured_fpr)?;
+        writeln!(f, "estimated fpr:\t{:.6}", self.estimated_fpr)?;
+        if self.histogram.is_empty() {
+            return Ok(());
+        }
+        let buckets = self.histogram.len();
+        writeln!(f, "slot histogram (chi-squared {:.1} over {} buckets):", self.chi_squared(), buckets)?;
+        let widest = self.histogram.iter().copied().max().unwrap_or(0).max(1);
+        for (b, &hits) in self.histogram.iter().enumerate() {
+            let (start, end) = (bucket_start(b, buckets, self.slot_count), bucket_start(b + 1, buckets, self.slot_count));
+            let bar = "#".repeat((hits * 50 / widest) as usize);
+            writeln!(f, "{start:>10}..{end:<10} {hits:>8} {bar}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bloom_filter::BloomFilter;
+    use super::measure;
+
+    #[test]
+    fn test_measure_bloom_filter() {
+        let mut bf = BloomFilter::new(10_000, 0.01);
+        let measurement = measure(&mut bf, 10_000, 100_000, 16, |id| format!("key-{id}"));
+
+        assert_eq!(measurement.false_positives as f64 / 100_000.0, measurement.observed_fpr);
+        assert_eq!(measurement.configured_fpr, 0.01);
+        assert!(measurement.observed_fpr < 0.02, "observed {}", measurement.observed_fpr);
+        assert!((measurement.observed_fpr - measurement.estimated_fpr).abs() < 0.005);
+        assert_eq!(measurement.histogram.len(), 16);
+        // 15 degrees of freedom: anything this large is far outside a uniform spread
+        assert!(measurement.chi_squared() < 60.0, "chi-squared {}", measurement.chi_squared());
+    }
+
+    #[test]
+    fn test_histogram_counts_every_slot() {
+        let mut bf = BloomFilter::new(100, 0.05);
+        let measurement = measure(&mut bf, 100, 0, 7, |id| id.to_string());
+        let k = measurement.histogram.iter().sum::<u64>() / 100;
+        assert!(k >= 1);
+        assert_eq!(measurement.histogram.iter().sum::<u64>(), k * 100);
+        assert_eq!(measurement.observed_fpr, 0.0);
+    }
+}
@@ -0,0 +1,269 @@
+use std::fmt::{Debug, Formatter};
+use std::hash::Hash;
+use crate::map::hash_map::HashMap;
+use crate::set::{EnumerableSet, RemovableSet, Set};
+
+/// An exact hash set; the keys of a Robin Hood `HashMap` with no values.
+pub struct HashSet<T: Hash + Eq> {
+    map: HashMap<T, ()>,
+}
+
+impl<T: Hash + Eq> HashSet<T> {
+    pub fn new() -> Self {
+        HashSet { map: HashMap::new() }
+    }
+    pub fn with_capacity(capacity: usize) -> Self {
+        HashSet { map: HashMap::with_capacity(capacity) }
+    }
+    pub fn with_load_factor(capacity: usize, load_factor: f64) -> crate::Result<Self> {
+        Ok(HashSet { map: HashMap::with_load_factor(capacity, load_factor)? })
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+    /// Number of elements the set can hold without growing.
+    pub fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+    pub fn load_factor(&self) -> f64 {
+        self.map.load_factor()
+    }
+    /// Makes room for at least `additional` more elements.
+    pub fn reserve(&mut self, additional: usize) {
+        self.map.reserve(additional)
+    }
+    pub fn shrink_to_fit(&mut self) {
+        self.map.shrink_to_fit()
+    }
+
+    /// Adds `value`, returning whether it was newly inserted.
+    pub fn insert(&mut self, value: T) -> bool {
+        self.map.insert(value, ()).is_none()
+    }
+    pub fn contains(&self, value: &T) -> bool {
+        self.map.contains_key(value)
+    }
+    pub fn get(&self, value: &T) -> Option<&T> {
+        self.map.get_key_value(value).map(|(value, _)| value)
+    }
+    pub fn remove(&mut self, value: &T) -> bool {
+        self.map.remove(value).is_some()
+    }
+    /// Removes and returns the stored element equal to `value`.
+    pub fn take(&mut self, value: &T) -> Option<T> {
+        self.map.remove_entry(value).map(|(value, _)| value)
+    }
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        self.map.keys()
+    }
+}
+
+impl<T: Hash + Eq> From<HashMap<T, ()>> for HashSet<T> {
+    fn from(map: HashMap<T, ()>) -> Self {
+        HashSet { map }
+    }
+}
+
+impl<T: Hash + Eq> Default for HashSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Hash + Eq + Clone> Clone for HashSet<T> {
+    fn clone(&self) -> Self {
+        HashSet { map: self.map.clone() }
+    }
+}
+
+impl<T: Hash + Eq + Debug> Debug for HashSet<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Hash + Eq> FromIterator<T> for HashSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = HashSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<T: Hash + Eq> Extend<T> for HashSet<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl<T: Hash + Eq + Clone> Set for HashSet<T> {
+    type T = T;
+
+    fn insert(&mut self, e: T) {
+        self.insert(e);
+    }
+    fn contains(&self, e: T) -> bool {
+        self.contains(&e)
+    }
+
+    fn equals(&self, other: Self) -> bool {
+        self.len() == other.len() && self.iter().all(|e| other.contains(e))
+    }
+
+    fn union(&self, other: Self) -> crate::Result<Self> {
+        let mut union = other;
+        union.extend(self.iter().cloned());
+        Ok(union)
+    }
+
+    fn intersection(&self, other: Self) -> crate::Result<Self> {
+        Ok(self.iter().filter(|e| other.contains(e)).cloned().collect())
+    }
+
+    fn difference(&self, other: Self) -> crate::Result<Self> {
+        Ok(self.iter().filter(|e| !other.contains(e)).cloned().collect())
+    }
+}
+
+impl<T: Hash + Eq + Clone> RemovableSet for HashSet<T> {
+    fn remove(&mut self, e: T) -> bool {
+        self.remove(&e)
+    }
+    fn clear(&mut self) {
+        self.clear()
+    }
+}
+
+impl<T: Hash + Eq + Clone> EnumerableSet for HashSet<T> {
+    fn len(&self) -> usize {
+        self.map.len()
+    }
+    fn iter(&self) -> Box<dyn Iterator<Item = T> + '_> {
+        Box::new(self.iter().cloned())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use crate::set::conformance::{self, Config};
+    use crate::set::{EnumerableSet, Membership, Set};
+    use super::HashSet;
+
+    #[test]
+    fn test_insert_contains_remove() {
+        let mut set: HashSet<String> = HashSet::new();
+        assert!(set.insert(String::from("A")));
+        assert!(!set.insert(String::from("A")));
+        assert!(set.insert(String::from("B")));
+        assert_eq!(set.len(), 2);
+
+        assert!(set.contains(&String::from("A")));
+        assert!(set.remove(&String::from("A")));
+        assert!(!set.remove(&String::from("A")));
+        assert!(!set.contains(&String::from("A")));
+        assert!(set.contains(&String::from("B")));
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn test_random_operations_match_std() {
+        let mut rng = StdRng::seed_from_u64(28);
+        let mut set: HashSet<u32> = HashSet::new();
+        let mut expected: std::collections::HashSet<u32> = std::collections::HashSet::new();
+        for _ in 0..20_000 {
+            let value = rng.gen_range(0..2_000);
+            if rng.gen_bool(0.6) {
+                assert_eq!(set.insert(value), expected.insert(value));
+            } else {
+                assert_eq!(set.remove(&value), expected.remove(&value));
+            }
+            assert_eq!(set.len(), expected.len());
+        }
+        for value in 0..2_000 {
+            assert_eq!(set.contains(&value), expected.contains(&value));
+        }
+        assert_eq!(set.iter().count(), expected.len());
+    }
+
+    #[test]
+    fn test_capacity_and_load_factor() {
+        let mut set: HashSet<u32> = HashSet::with_load_factor(100, 0.5).unwrap();
+        assert!(set.capacity() >= 100);
+        let capacity = set.capacity();
+        set.extend(0..100);
+        assert_eq!(set.capacity(), capacity);
+
+        set.extend(100..1_000);
+        assert!(set.capacity() >= 1_000);
+        assert_eq!(set.load_factor(), 0.5);
+
+        for value in 10..1_000 {
+            set.remove(&value);
+        }
+        set.shrink_to_fit();
+        assert_eq!(set.capacity(), 16);
+        assert!((0..10).all(|value| set.contains(&value)));
+
+        assert!(HashSet::<u32>::with_load_factor(10, 1.0).is_err());
+        assert!(HashSet::<u32>::with_load_factor(10, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a: HashSet<&str> = ["A", "B"].into_iter().collect();
+        let b: HashSet<&str> = ["B", "C"].into_iter().collect();
+
+        let union = a.union(b.clone()).unwrap();
+        assert!(union.equals(["A", "B", "C"].into_iter().collect()));
+
+        let intersection = a.intersection(b.clone()).unwrap();
+        assert!(intersection.equals(["B"].into_iter().collect()));
+
+        let difference = a.difference(b.clone()).unwrap();
+        assert!(difference.equals(["A"].into_iter().collect()));
+
+        let symmetric_difference = a.symmetric_difference(b).unwrap();
+        assert!(symmetric_difference.equals(["A", "C"].into_iter().collect()));
+    }
+
+    #[test]
+    fn test_subset_and_disjoint() {
+        let small: HashSet<u32> = (0..5).collect();
+        let large: HashSet<u32> = (0..10).collect();
+        let other: HashSet<u32> = (10..20).collect();
+
+        assert!(small.is_subset(&large));
+        assert!(!large.is_subset(&small));
+        assert!(large.is_superset(&small));
+        assert!(small.is_disjoint(&other));
+        assert!(!small.is_disjoint(&large));
+    }
+
+    #[test]
+    fn test_membership_is_exact() {
+        let set: HashSet<u32> = (0..5).collect();
+        assert!(set.error_bounds().is_exact());
+        assert_eq!(set.membership(3), Membership::Definitely);
+        assert_eq!(set.membership(7), Membership::DefinitelyNot);
+    }
+
+    #[test]
+    fn test_conformance() {
+        let config = Config::default();
+        conformance::check_removable_set(&config, HashSet::new, |id| id);
+        conformance::check_enumerable_set(&config, HashSet::new, |id| id);
+    }
+}
@@ -0,0 +1,268 @@
+use std::fmt::{Debug, Formatter};
+use std::ops::Range;
+use crate::map::interval_tree::IntervalTree;
+use crate::set::{EnumerableSet, RemovableSet, Set};
+
+/// A set of points stored as disjoint half-open ranges.
+///
+/// Inserted ranges that overlap or touch are merged, so the stored ranges never share or abut
+/// an end. As a `Set` its elements are ranges: `contains` asks whether every point of a range is
+/// covered, and empty ranges are ignored by `insert` and always covered.
+pub struct IntervalSet<K: Ord> {
+    tree: IntervalTree<K, ()>,
+}
+
+impl<K: Ord + Clone> IntervalSet<K> {
+    pub fn new() -> Self {
+        IntervalSet { tree: IntervalTree::new() }
+    }
+
+    /// Number of disjoint ranges.
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Adds every point of `range`, merging it with the ranges it overlaps or touches.
+    pub fn insert(&mut self, range: Range<K>) {
+        if range.is_empty() {
+            return;
+        }
+        let merged: Vec<Range<K>> = self.tree.touching(&range).map(|(r, _)| r.clone()).collect();
+        let (mut start, mut end) = (range.start, range.end);
+        for r in merged {
+            self.tree.remove(&r);
+            start = start.min(r.start);
+            end = end.max(r.end);
+        }
+        self.tree.insert(start..end, ());
+    }
+    /// Removes every point of `range`, returning whether any was present.
+    pub fn remove(&mut self, range: &Range<K>) -> bool {
+        let cut: Vec<Range<K>> = self.tree.overlapping(range).map(|(r, _)| r.clone()).collect();
+        for r in &cut {
+            self.tree.remove(r);
+            if r.start < range.start {
+                self.tree.insert(r.start.clone()..range.start.clone(), ());
+            }
+            if range.end < r.end {
+                self.tree.insert(range.end.clone()..r.end.clone(), ());
+            }
+        }
+        !cut.is_empty()
+    }
+    pub fn contains_point(&self, point: &K) -> bool {
+        self.tree.stab(point).next().is_some()
+    }
+    /// Whether every point of `range` is in the set.
+    pub fn contains(&self, range: &Range<K>) -> bool {
+        range.is_empty() || self.tree.stab(&range.start).next().is_some_and(|(r, _)| r.end >= range.end)
+    }
+    /// The stored range holding `point`, if any.
+    pub fn range_containing(&self, point: &K) -> Option<&Range<K>> {
+        self.tree.stab(point).next().map(|(r, _)| r)
+    }
+    pub fn clear(&mut self) {
+        self.tree.clear()
+    }
+
+    /// The disjoint ranges in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = &Range<K>> + '_ {
+        self.tree.iter().map(|(r, _)| r)
+    }
+}
+
+impl<K: Ord + Clone> Default for IntervalSet<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord + Clone> Clone for IntervalSet<K> {
+    fn clone(&self) -> Self {
+        IntervalSet { tree: self.tree.clone() }
+    }
+}
+
+impl<K: Ord + Clone> PartialEq for IntervalSet<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<K: Ord + Clone + Debug> Debug for IntervalSet<K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Ord + Clone> FromIterator<Range<K>> for IntervalSet<K> {
+    fn from_iter<I: IntoIterator<Item = Range<K>>>(iter: I) -> Self {
+        let mut set = IntervalSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<K: Ord + Clone> Extend<Range<K>> for IntervalSet<K> {
+    fn extend<I: IntoIterator<Item = Range<K>>>(&mut self, iter: I) {
+        for range in iter {
+            self.insert(range);
+        }
+    }
+}
+
+impl<K: Ord + Clone> Set for IntervalSet<K> {
+    type T = Range<K>;
+
+    fn insert(&mut self, e: Range<K>) {
+        self.insert(e)
+    }
+    fn contains(&self, e: Range<K>) -> bool {
+        self.contains(&e)
+    }
+
+    fn equals(&self, other: Self) -> bool {
+        *self == other
+    }
+
+    fn union(&self, other: Self) -> crate::Result<Self> {
+        let mut union = self.clone();
+        union.extend(other.iter().cloned());
+        Ok(union)
+    }
+
+    fn intersection(&self, other: Self) -> crate::Result<Self> {
+        // Both sides are sorted and disjoint, so one sweep pairs up every overlap
+        let mut intersection = IntervalSet::new();
+        let mut left = self.iter().peekable();
+        let mut right = other.iter().peekable();
+        while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+            let start = (&l.start).max(&r.start);
+            let end = (&l.end).min(&r.end);
+            if start < end {
+                intersection.tree.insert(start.clone()..end.clone(), ());
+            }
+            if l.end < r.end {
+                left.next();
+            } else {
+                right.next();
+            }
+        }
+        Ok(intersection)
+    }
+
+    fn difference(&self, other: Self) -> crate::Result<Self> {
+        let mut difference = self.clone();
+        for range in other.iter() {
+            difference.remove(range);
+        }
+        Ok(difference)
+    }
+}
+
+impl<K: Ord + Clone> RemovableSet for IntervalSet<K> {
+    fn remove(&mut self, e: Range<K>) -> bool {
+        self.remove(&e)
+    }
+    fn clear(&mut self) {
+        self.clear()
+    }
+}
+
+impl<K: Ord + Clone> EnumerableSet for IntervalSet<K> {
+    fn len(&self) -> usize {
+        self.tree.len()
+    }
+    fn iter(&self) -> Box<dyn Iterator<Item = Range<K>> + '_> {
+        Box::new(self.iter().cloned())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+    use std::ops::Range;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use crate::set::conformance::{self, Config};
+    use crate::set::{EnumerableSet, Set};
+    use super::IntervalSet;
+
+    fn ranges(set: &IntervalSet<u32>) -> Vec<Range<u32>> {
+        set.iter().cloned().collect()
+    }
+
+    fn points(set: &IntervalSet<u32>) -> BTreeSet<u32> {
+        set.iter().flat_map(|r| r.clone()).collect()
+    }
+
+    #[test]
+    fn test_insert_merges() {
+        let mut set: IntervalSet<u32> = [0..5, 10..15, 20..25].into_iter().collect();
+        assert_eq!(set.len(), 3);
+        set.insert(5..10);
+        assert_eq!(ranges(&set), vec![0..15, 20..25]);
+        set.insert(12..22);
+        assert_eq!(ranges(&set), vec![0..25]);
+        set.insert(30..30);
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn test_remove_splits() {
+        let mut set = IntervalSet::new();
+        set.insert(0..20);
+        assert!(set.remove(&(5..10)));
+        assert!(!set.remove(&(6..8)));
+        assert_eq!(ranges(&set), vec![0..5, 10..20]);
+        assert!(set.contains(&(10..20)));
+        assert!(!set.contains(&(4..11)));
+        assert!(set.contains_point(&4) && !set.contains_point(&5));
+        assert_eq!(set.range_containing(&15), Some(&(10..20)));
+    }
+
+    #[test]
+    fn test_random_operations_match_points() {
+        let mut rng = StdRng::seed_from_u64(37);
+        let mut set = IntervalSet::new();
+        let mut expected = BTreeSet::new();
+        for _ in 0..2_000 {
+            let start = rng.gen_range(0..300);
+            let range = start..start + rng.gen_range(0..20);
+            if rng.gen_bool(0.6) {
+                expected.extend(range.clone());
+                set.insert(range);
+            } else {
+                let was_present = range.clone().any(|point| expected.remove(&point));
+                expected.retain(|point| !range.contains(point));
+                assert_eq!(set.remove(&range), was_present);
+            }
+        }
+        assert_eq!(points(&set), expected);
+        // Merged ranges neither overlap nor touch
+        assert!(set.iter().zip(set.iter().skip(1)).all(|(a, b)| a.end < b.start));
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a: IntervalSet<u32> = [0..10, 20..30].into_iter().collect();
+        let b: IntervalSet<u32> = [5..25, 28..40].into_iter().collect();
+
+        assert_eq!(ranges(&a.union(b.clone()).unwrap()), vec![0..40]);
+        assert_eq!(ranges(&a.intersection(b.clone()).unwrap()), vec![5..10, 20..25, 28..30]);
+        assert_eq!(ranges(&a.difference(b.clone()).unwrap()), vec![0..5, 25..28]);
+        assert_eq!(ranges(&a.symmetric_difference(b.clone()).unwrap()), vec![0..5, 10..20, 25..28, 30..40]);
+        assert!(a.intersection(b.clone()).unwrap().is_subset(&a));
+        assert!(!a.is_subset(&b));
+    }
+
+    #[test]
+    fn test_conformance() {
+        let config = Config::default();
+        conformance::check_removable_set(&config, IntervalSet::new, |id| id * 2..id * 2 + 1);
+        conformance::check_enumerable_set(&config, IntervalSet::new, |id| id * 2..id * 2 + 1);
+    }
+}
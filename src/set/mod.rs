@@ -1,4 +1,57 @@
+use std::sync::RwLock;
 use crate::Result;
+
+pub mod concurrent;
+pub mod conformance;
+pub mod disjoint_set;
+pub mod fpr;
+pub mod hash_set;
+pub mod interval_set;
+pub mod multiset;
+pub mod persistent;
+pub mod radix_tree;
+pub mod roaring;
+pub mod sorted_set;
+
+/// The answer to a membership query, qualified by how much it can be trusted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Membership {
+    Definitely,
+    /// The element was reported present, but `fpr` is the chance that it never was.
+    Probably { fpr: f64 },
+    DefinitelyNot,
+    /// The element was reported absent, but the set may have lost it along the way.
+    ProbablyNot,
+}
+
+impl Membership {
+    pub fn is_member(&self) -> bool {
+        matches!(self, Membership::Definitely | Membership::Probably { .. })
+    }
+    pub fn is_certain(&self) -> bool {
+        matches!(self, Membership::Definitely | Membership::DefinitelyNot)
+    }
+}
+
+/// The kinds of error carried by a set's answers to `contains`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ErrorBounds {
+    // Chance that an absent element is reported present
+    pub false_positive_rate: f64,
+    // Whether an inserted element may be reported absent
+    pub false_negatives: bool,
+}
+
+impl ErrorBounds {
+    pub const EXACT: ErrorBounds = ErrorBounds { false_positive_rate: 0.0, false_negatives: false };
+
+    pub fn is_exact(&self) -> bool {
+        self.false_positive_rate == 0.0 && !self.false_negatives
+    }
+}
+
+/// Union, intersection and difference never make an exact set approximate; approximate sets
+/// report any error an operation introduced through the result's `error_bounds`.
 pub trait Set {
     type T;
     fn insert(&mut self, e: Self::T);
@@ -7,6 +60,110 @@ pub trait Set {
     fn union(&self, other: Self) -> Result<Self> where Self: Sized;
     fn intersection(&self, other: Self) -> Result<Self> where Self: Sized;
     fn difference(&self, other: Self) -> Result<Self> where Self: Sized;
+
+    fn error_bounds(&self) -> ErrorBounds {
+        ErrorBounds::EXACT
+    }
+    /// Like `contains`, but says whether the answer is certain.
+    fn membership(&self, e: Self::T) -> Membership {
+        let bounds = self.error_bounds();
+        match self.contains(e) {
+            true if bounds.false_positive_rate > 0.0 => Membership::Probably { fpr: bounds.false_positive_rate },
+            true => Membership::Definitely,
+            false if bounds.false_negatives => Membership::ProbablyNot,
+            false => Membership::DefinitelyNot,
+        }
+    }
 }
 
+/// A set that can forget individual elements, or all of them at once.
+pub trait RemovableSet: Set {
+    /// Removes `e`, returning whether it was present.
+    fn remove(&mut self, e: Self::T) -> bool;
+    fn clear(&mut self);
+}
+
+/// A set that knows exactly which elements it holds.
+pub trait EnumerableSet: Set {
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Yields an owned copy of every element, in no particular order.
+    fn iter(&self) -> Box<dyn Iterator<Item = Self::T> + '_>;
+
+    fn is_subset<S: Set<T = Self::T>>(&self, other: &S) -> bool {
+        self.iter().all(|e| other.contains(e))
+    }
+    fn is_superset<S: EnumerableSet<T = Self::T>>(&self, other: &S) -> bool {
+        other.iter().all(|e| self.contains(e))
+    }
+    fn is_disjoint<S: Set<T = Self::T>>(&self, other: &S) -> bool {
+        self.iter().all(|e| !other.contains(e))
+    }
+    fn symmetric_difference(&self, other: Self) -> Result<Self>
+        where
+            Self: Sized + Clone,
+    {
+        let left = self.difference(other.clone())?;
+        let right = other.difference(self.clone())?;
+        left.union(right)
+    }
+}
+
+/// A probabilistic set whose `contains` may answer `true` for elements that were never inserted.
+pub trait ApproximateSet: Set {
+    /// The false positive probability the set was configured for.
+    fn false_positive_probability(&self) -> f64;
+    /// The false positive probability implied by how full the set currently is.
+    fn estimated_fpr(&self) -> f64;
+    /// Number of slots (bits, buckets, ...) that elements hash to, or 0 if they are not exposed.
+    fn slot_count(&self) -> usize {
+        0
+    }
+    /// The slots `e` hashes to, for checking how evenly elements spread over them.
+    fn slots(&self, _e: Self::T) -> Vec<usize> {
+        Vec::new()
+    }
+}
 
+/// A set that can be changed through a shared reference, so several threads can use it at once.
+///
+/// Generic code bounded by this trait runs unchanged on a `ConcurrentSet` or on any exact set
+/// behind a single `RwLock`.
+pub trait SharedSet {
+    type T;
+    /// Adds `e`, returning whether it was absent.
+    fn insert(&self, e: Self::T) -> bool;
+    fn contains(&self, e: Self::T) -> bool;
+    /// Removes `e`, returning whether it was present.
+    fn remove(&self, e: Self::T) -> bool;
+    fn clear(&self);
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<S: RemovableSet + EnumerableSet> SharedSet for RwLock<S> where S::T: Clone {
+    type T = S::T;
+
+    fn insert(&self, e: S::T) -> bool {
+        let mut set = self.write().expect("A writer panicked while holding the set");
+        let absent = !set.contains(e.clone());
+        set.insert(e);
+        absent
+    }
+    fn contains(&self, e: S::T) -> bool {
+        self.read().expect("A writer panicked while holding the set").contains(e)
+    }
+    fn remove(&self, e: S::T) -> bool {
+        self.write().expect("A writer panicked while holding the set").remove(e)
+    }
+    fn clear(&self) {
+        self.write().expect("A writer panicked while holding the set").clear()
+    }
+    fn len(&self) -> usize {
+        self.read().expect("A writer panicked while holding the set").len()
+    }
+}
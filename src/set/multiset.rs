@@ -0,0 +1,354 @@
+use std::fmt::{Debug, Formatter};
+use std::hash::Hash;
+use std::iter;
+use crate::map::hash_map::HashMap;
+use crate::set::{EnumerableSet, RemovableSet, Set};
+
+/// A bag: like a set, but it remembers how many times each element was inserted.
+///
+/// Elements with a count of zero are never stored, so `distinct_len` is the number of keys.
+/// Occurrences saturate: once `len` reaches `usize::MAX`, further insertions are dropped, so `len`
+/// always equals the sum of the counts.
+///
+/// Through `Set`, `RemovableSet` and `EnumerableSet` a multiset reads as the set of its distinct
+/// elements, each occurring once: `insert` adds an occurrence only if the element is absent,
+/// `remove` takes away every occurrence, and `len` and `iter` count each element once. The
+/// inherent methods keep full multiset semantics.
+pub struct Multiset<T: Hash + Eq> {
+    counts: HashMap<T, usize>,
+    // Total occurrences over all elements
+    len: usize,
+}
+
+impl<T: Hash + Eq> Multiset<T> {
+    pub fn new() -> Self {
+        Multiset { counts: HashMap::new(), len: 0 }
+    }
+
+    /// Total number of occurrences.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    /// Number of distinct elements.
+    pub fn distinct_len(&self) -> usize {
+        self.counts.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn count(&self, value: &T) -> usize {
+        self.counts.get(value).copied().unwrap_or(0)
+    }
+    pub fn contains(&self, value: &T) -> bool {
+        self.counts.contains_key(value)
+    }
+
+    /// Adds one occurrence of `value`, returning its previous count.
+    pub fn insert(&mut self, value: T) -> usize {
+        self.insert_n(value, 1)
+    }
+    /// Adds `n` occurrences of `value`, or as many as fit below `usize::MAX` in total, returning
+    /// its previous count.
+    pub fn insert_n(&mut self, value: T, n: usize) -> usize {
+        // No count exceeds `len`, so neither can overflow once `len` can't
+        let n = n.min(usize::MAX - self.len);
+        if n == 0 {
+            return self.count(&value);
+        }
+        let count = self.counts.get_or_insert_with(value, || 0);
+        let previous = *count;
+        *count += n;
+        self.len += n;
+        previous
+    }
+    /// Removes one occurrence of `value`, returning whether there was one.
+    pub fn remove(&mut self, value: &T) -> bool {
+        self.remove_n(value, 1) == 1
+    }
+    /// Removes up to `n` occurrences of `value`, returning how many were removed.
+    pub fn remove_n(&mut self, value: &T, n: usize) -> usize {
+        let Some(count) = self.counts.get_mut(value) else {
+            return 0;
+        };
+        let removed = n.min(*count);
+        *count -= removed;
+        if *count == 0 {
+            self.counts.remove(value);
+        }
+        self.len -= removed;
+        removed
+    }
+    /// Removes every occurrence of `value`, returning how many there were.
+    pub fn remove_all(&mut self, value: &T) -> usize {
+        let removed = self.counts.remove(value).unwrap_or(0);
+        self.len -= removed;
+        removed
+    }
+    pub fn clear(&mut self) {
+        self.counts.clear();
+        self.len = 0;
+    }
+
+    /// Each distinct element with its count, in no particular order.
+    pub fn counts(&self) -> impl Iterator<Item = (&T, usize)> + '_ {
+        self.counts.iter().map(|(value, &count)| (value, count))
+    }
+    /// Each distinct element once.
+    pub fn distinct(&self) -> impl Iterator<Item = &T> + '_ {
+        self.counts.keys()
+    }
+    /// Every occurrence, so each element appears as many times as its count.
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        self.counts().flat_map(|(value, count)| iter::repeat_n(value, count))
+    }
+
+    /// Whether every element occurs in `other` at least as often as here.
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.len <= other.len && self.counts().all(|(value, count)| count <= other.count(value))
+    }
+
+    /// Builds a multiset holding each element of `set` once.
+    pub fn from_set<S: EnumerableSet<T = T>>(set: &S) -> Self {
+        set.iter().collect()
+    }
+    /// The distinct elements as any set.
+    pub fn to_set<S: Set<T = T> + Default>(&self) -> S where T: Clone {
+        let mut set = S::default();
+        for value in self.distinct() {
+            set.insert(value.clone());
+        }
+        set
+    }
+}
+
+impl<T: Hash + Eq + Clone> Multiset<T> {
+    /// Each element with the larger of its two counts.
+    pub fn union(&self, other: &Self) -> Self {
+        self.combine(other, usize::max)
+    }
+    /// Each element with its two counts added, saturating like `insert_n`.
+    pub fn sum(&self, other: &Self) -> Self {
+        self.combine(other, usize::saturating_add)
+    }
+    /// Each element with the smaller of its two counts.
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.combine(other, usize::min)
+    }
+    /// Each element with `other`'s count taken off its own, stopping at zero.
+    pub fn difference(&self, other: &Self) -> Self {
+        self.combine(other, usize::saturating_sub)
+    }
+
+    // Applies `f` to the counts of every element found in either side.
+    fn combine<F: Fn(usize, usize) -> usize>(&self, other: &Self, f: F) -> Self {
+        let mut combined = Multiset::new();
+        for (value, count) in self.counts() {
+            combined.insert_n(value.clone(), f(count, other.count(value)));
+        }
+        for (value, count) in other.counts().filter(|(value, _)| !self.contains(value)) {
+            combined.insert_n(value.clone(), f(0, count));
+        }
+        combined
+    }
+}
+
+impl<T: Hash + Eq + Clone> Set for Multiset<T> {
+    type T = T;
+
+    fn insert(&mut self, e: T) {
+        if !self.contains(&e) {
+            self.insert(e);
+        }
+    }
+    fn contains(&self, e: T) -> bool {
+        self.contains(&e)
+    }
+
+    fn equals(&self, other: Self) -> bool {
+        self.distinct_len() == other.distinct_len() && self.distinct().all(|e| other.contains(e))
+    }
+
+    fn union(&self, other: Self) -> crate::Result<Self> {
+        Ok(self.union(&other))
+    }
+
+    fn intersection(&self, other: Self) -> crate::Result<Self> {
+        Ok(self.intersection(&other))
+    }
+
+    // Unlike `Multiset::difference`, an element `other` holds goes however often it occurs here
+    fn difference(&self, other: Self) -> crate::Result<Self> {
+        let mut difference = Multiset::new();
+        for (value, count) in self.counts().filter(|(value, _)| !other.contains(value)) {
+            difference.insert_n(value.clone(), count);
+        }
+        Ok(difference)
+    }
+}
+
+impl<T: Hash + Eq + Clone> RemovableSet for Multiset<T> {
+    fn remove(&mut self, e: T) -> bool {
+        self.remove_all(&e) > 0
+    }
+    fn clear(&mut self) {
+        self.clear()
+    }
+}
+
+impl<T: Hash + Eq + Clone> EnumerableSet for Multiset<T> {
+    fn len(&self) -> usize {
+        self.distinct_len()
+    }
+    fn iter(&self) -> Box<dyn Iterator<Item = T> + '_> {
+        Box::new(self.distinct().cloned())
+    }
+}
+
+impl<T: Hash + Eq> Default for Multiset<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Hash + Eq + Clone> Clone for Multiset<T> {
+    fn clone(&self) -> Self {
+        Multiset { counts: self.counts.clone(), len: self.len }
+    }
+}
+
+impl<T: Hash + Eq> PartialEq for Multiset<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len
+            && self.distinct_len() == other.distinct_len()
+            && self.counts().all(|(value, count)| other.count(value) == count)
+    }
+}
+
+impl<T: Hash + Eq + Debug> Debug for Multiset<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.counts()).finish()
+    }
+}
+
+impl<T: Hash + Eq> FromIterator<T> for Multiset<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut multiset = Multiset::new();
+        multiset.extend(iter);
+        multiset
+    }
+}
+
+impl<T: Hash + Eq> Extend<T> for Multiset<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::set::conformance::{self, Config};
+    use crate::set::hash_set::HashSet;
+    use crate::set::sorted_set::SortedSet;
+    use crate::set::{EnumerableSet, RemovableSet, Set};
+    use super::Multiset;
+
+    fn bag(values: &[char]) -> Multiset<char> {
+        values.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_counts() {
+        let mut bag = bag(&['a', 'b', 'a']);
+        assert_eq!(bag.count(&'a'), 2);
+        assert_eq!(bag.count(&'c'), 0);
+        assert_eq!((bag.len(), bag.distinct_len()), (3, 2));
+
+        assert_eq!(bag.insert_n('c', 3), 0);
+        assert_eq!(bag.insert_n('a', 1), 2);
+        assert_eq!(bag.remove_n(&'c', 5), 3);
+        assert!(!bag.contains(&'c'));
+        assert!(bag.remove(&'b'));
+        assert!(!bag.remove(&'b'));
+        assert_eq!(bag.remove_all(&'a'), 3);
+        assert!(bag.is_empty());
+        assert_eq!(bag.distinct_len(), 0);
+    }
+
+    #[test]
+    fn test_counts_saturate() {
+        let mut bag = bag(&['a']);
+        assert_eq!(bag.insert_n('b', usize::MAX), 0);
+        assert_eq!((bag.count(&'a'), bag.count(&'b')), (1, usize::MAX - 1));
+        assert_eq!(bag.insert('c'), 0);
+        assert_eq!(bag.count(&'c'), 0);
+        assert_eq!(bag.len(), usize::MAX);
+
+        let sum = bag.sum(&bag);
+        assert_eq!(sum.len(), usize::MAX);
+        assert_eq!(sum.len(), sum.counts().map(|(_, count)| count).sum::<usize>());
+        assert_eq!(bag.remove_all(&'b'), usize::MAX - 1);
+        assert_eq!(bag.len(), 1);
+    }
+
+    #[test]
+    fn test_iteration() {
+        let bag = bag(&['x', 'y', 'x', 'x']);
+        let mut distinct: Vec<_> = bag.distinct().copied().collect();
+        distinct.sort();
+        assert_eq!(distinct, vec!['x', 'y']);
+        let mut all: Vec<_> = bag.iter().copied().collect();
+        all.sort();
+        assert_eq!(all, vec!['x', 'x', 'x', 'y']);
+    }
+
+    #[test]
+    fn test_multiset_operations() {
+        let a = bag(&['a', 'a', 'a', 'b', 'c']);
+        let b = bag(&['a', 'b', 'b', 'd']);
+
+        assert_eq!(a.union(&b), bag(&['a', 'a', 'a', 'b', 'b', 'c', 'd']));
+        assert_eq!(a.sum(&b), bag(&['a', 'a', 'a', 'a', 'b', 'b', 'b', 'c', 'd']));
+        assert_eq!(a.intersection(&b), bag(&['a', 'b']));
+        assert_eq!(a.difference(&b), bag(&['a', 'a', 'c']));
+        assert!(a.intersection(&b).is_subset(&a));
+        assert!(!a.is_subset(&b));
+    }
+
+    #[test]
+    fn test_set_conversions() {
+        let set: SortedSet<char> = ['b', 'a'].into_iter().collect();
+        let mut bag = Multiset::from_set(&set);
+        bag.insert('a');
+        assert_eq!(bag.count(&'a'), 2);
+        assert_eq!(bag.count(&'b'), 1);
+
+        let distinct: HashSet<char> = bag.to_set();
+        assert_eq!(distinct.len(), 2);
+        assert!(distinct.contains(&'a') && distinct.contains(&'b'));
+    }
+
+    #[test]
+    fn test_set_view_counts_each_element_once() {
+        let mut letters = bag(&['a', 'a', 'b']);
+        Set::insert(&mut letters, 'a');
+        Set::insert(&mut letters, 'c');
+        assert_eq!((letters.count(&'a'), letters.count(&'c')), (2, 1));
+        assert_eq!(EnumerableSet::len(&letters), 3);
+        assert!(Set::equals(&letters, bag(&['a', 'b', 'c'])));
+
+        let difference = Set::difference(&letters, bag(&['a'])).unwrap();
+        assert_eq!(difference, bag(&['b', 'c']));
+        assert!(RemovableSet::remove(&mut letters, 'a'));
+        assert!(!Set::contains(&letters, 'a'));
+        assert!(!RemovableSet::remove(&mut letters, 'a'));
+    }
+
+    #[test]
+    fn test_conformance() {
+        let config = Config::default();
+        conformance::check_removable_set(&config, Multiset::new, |id| id);
+        conformance::check_enumerable_set(&config, Multiset::new, |id| id);
+    }
+}
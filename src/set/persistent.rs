@@ -0,0 +1,192 @@
+use std::fmt::{Debug, Formatter};
+use std::hash::Hash;
+use crate::map::persistent::PersistentMap;
+use crate::set::{EnumerableSet, RemovableSet, Set};
+
+/// An immutable hash set whose versions share structure; see `PersistentMap`.
+pub struct PersistentSet<T> {
+    map: PersistentMap<T, ()>,
+}
+
+impl<T: Hash + Eq> PersistentSet<T> {
+    pub fn new() -> Self {
+        PersistentSet { map: PersistentMap::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+    pub fn contains(&self, value: &T) -> bool {
+        self.map.contains_key(value)
+    }
+    pub fn get(&self, value: &T) -> Option<&T> {
+        self.map.get_key_value(value).map(|(value, _)| value)
+    }
+    /// A new version that also holds `value`.
+    pub fn insert(&self, value: T) -> Self {
+        PersistentSet { map: self.map.insert(value, ()) }
+    }
+    /// A new version without `value`.
+    pub fn remove(&self, value: &T) -> Self {
+        PersistentSet { map: self.map.remove(value) }
+    }
+    /// Whether both are the very same version, in O(1).
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        self.map.ptr_eq(&other.map)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        self.map.keys()
+    }
+}
+
+impl<T> Clone for PersistentSet<T> {
+    fn clone(&self) -> Self {
+        PersistentSet { map: self.map.clone() }
+    }
+}
+
+impl<T: Hash + Eq> Default for PersistentSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Hash + Eq> PartialEq for PersistentSet<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<T: Hash + Eq + Debug> Debug for PersistentSet<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Hash + Eq> FromIterator<T> for PersistentSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        PersistentSet { map: iter.into_iter().map(|value| (value, ())).collect() }
+    }
+}
+
+// The `&mut self` methods swap in the new version; earlier clones keep seeing the old one.
+impl<T: Hash + Eq + Clone> Set for PersistentSet<T> {
+    type T = T;
+
+    fn insert(&mut self, e: T) {
+        *self = PersistentSet::insert(self, e);
+    }
+    fn contains(&self, e: T) -> bool {
+        PersistentSet::contains(self, &e)
+    }
+
+    fn equals(&self, other: Self) -> bool {
+        *self == other
+    }
+
+    fn union(&self, other: Self) -> crate::Result<Self> {
+        if self.ptr_eq(&other) {
+            return Ok(other);
+        }
+        let (small, large) = if self.len() < other.len() { (self, &other) } else { (&other, self) };
+        Ok(small.iter().fold(large.clone(), |union, e| union.insert(e.clone())))
+    }
+
+    fn intersection(&self, other: Self) -> crate::Result<Self> {
+        if self.ptr_eq(&other) {
+            return Ok(other);
+        }
+        Ok(self.iter().filter(|e| other.contains(e)).cloned().collect())
+    }
+
+    fn difference(&self, other: Self) -> crate::Result<Self> {
+        if self.ptr_eq(&other) {
+            return Ok(PersistentSet::new());
+        }
+        Ok(other.iter().fold(self.clone(), |difference, e| difference.remove(e)))
+    }
+}
+
+impl<T: Hash + Eq + Clone> RemovableSet for PersistentSet<T> {
+    fn remove(&mut self, e: T) -> bool {
+        let removed = PersistentSet::remove(self, &e);
+        let was_present = removed.len() < self.len();
+        *self = removed;
+        was_present
+    }
+    fn clear(&mut self) {
+        *self = PersistentSet::new();
+    }
+}
+
+impl<T: Hash + Eq + Clone> EnumerableSet for PersistentSet<T> {
+    fn len(&self) -> usize {
+        self.map.len()
+    }
+    fn iter(&self) -> Box<dyn Iterator<Item = T> + '_> {
+        Box::new(self.map.keys().cloned())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::set::conformance::{self, Config};
+    use crate::set::{EnumerableSet, RemovableSet, Set};
+    use super::PersistentSet;
+
+    #[test]
+    fn test_versions_are_independent() {
+        let v1: PersistentSet<&str> = ["a", "b"].into_iter().collect();
+        let v2 = v1.insert("c");
+        let v3 = v2.remove(&"a");
+
+        assert_eq!(v1.len(), 2);
+        assert!(v1.contains(&"a") && !v1.contains(&"c"));
+        assert!(v2.contains(&"a") && v2.contains(&"c"));
+        assert!(!v3.contains(&"a") && v3.contains(&"c"));
+    }
+
+    #[test]
+    fn test_set_trait_swaps_versions() {
+        let mut set: PersistentSet<u32> = (0..10).collect();
+        let snapshot = set.clone();
+        Set::insert(&mut set, 10);
+        assert!(RemovableSet::remove(&mut set, 0));
+        assert!(!RemovableSet::remove(&mut set, 0));
+
+        assert_eq!(EnumerableSet::len(&set), 10);
+        assert!(!set.contains(&0));
+        assert!(snapshot.contains(&0) && !snapshot.contains(&10));
+    }
+
+    #[test]
+    fn test_equals() {
+        let a: PersistentSet<u32> = (0..100).collect();
+        assert!(a.equals(a.clone()));
+        assert!(a.equals((0..100).rev().collect()));
+        assert!(!a.equals(a.remove(&50)));
+        assert!(a.equals(a.insert(100).remove(&100)));
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a: PersistentSet<u32> = (0..6).collect();
+        let b: PersistentSet<u32> = (3..9).collect();
+
+        assert!(a.union(b.clone()).unwrap().equals((0..9).collect()));
+        assert!(a.intersection(b.clone()).unwrap().equals((3..6).collect()));
+        assert!(a.difference(b.clone()).unwrap().equals((0..3).collect()));
+        assert!(a.symmetric_difference(b).unwrap().equals([0, 1, 2, 6, 7, 8].into_iter().collect()));
+        assert!(a.difference(a.clone()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_conformance() {
+        let config = Config::default();
+        conformance::check_removable_set(&config, PersistentSet::new, |id| id);
+        conformance::check_enumerable_set(&config, PersistentSet::new, |id| id);
+    }
+}
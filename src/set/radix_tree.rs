@@ -0,0 +1,411 @@
+use std::fmt::{Debug, Formatter};
+use std::mem;
+use crate::set::{EnumerableSet, RemovableSet, Set};
+
+#[derive(Clone)]
+struct Node {
+    // Edge label from the parent; empty only for the root
+    prefix: Vec<u8>,
+    // Whether the path ending here is a stored key
+    terminal: bool,
+    // Ordered by the first byte of their prefix, which is unique among siblings
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn new(prefix: Vec<u8>, terminal: bool) -> Self {
+        Node { prefix, terminal, children: Vec::new() }
+    }
+
+    fn child(&self, byte: u8) -> Result<usize, usize> {
+        self.children.binary_search_by_key(&byte, |child| child.prefix[0])
+    }
+
+    // `key` is what remains after this node's prefix.
+    fn insert(&mut self, key: &[u8]) -> bool {
+        if key.is_empty() {
+            return !mem::replace(&mut self.terminal, true);
+        }
+        match self.child(key[0]) {
+            Err(i) => {
+                self.children.insert(i, Node::new(key.to_vec(), true));
+                true
+            }
+            Ok(i) => {
+                let child = &mut self.children[i];
+                let common = common_prefix(&child.prefix, key);
+                if common < child.prefix.len() {
+                    child.split(common);
+                }
+                child.insert(&key[common..])
+            }
+        }
+    }
+
+    // Cuts the prefix at `at`, moving the rest of it and everything below into a single child.
+    fn split(&mut self, at: usize) {
+        let tail = Node {
+            prefix: self.prefix.split_off(at),
+            terminal: mem::replace(&mut self.terminal, false),
+            children: mem::take(&mut self.children),
+        };
+        self.children.push(tail);
+    }
+
+    fn remove(&mut self, key: &[u8]) -> bool {
+        if key.is_empty() {
+            return mem::replace(&mut self.terminal, false);
+        }
+        let Ok(i) = self.child(key[0]) else {
+            return false;
+        };
+        let child = &mut self.children[i];
+        if !key.starts_with(&child.prefix) {
+            return false;
+        }
+        let prefix_len = child.prefix.len();
+        if !child.remove(&key[prefix_len..]) {
+            return false;
+        }
+        // Keep the tree compressed: no empty leaves, no pass-through nodes below the root
+        if !child.terminal {
+            match child.children.len() {
+                0 => {
+                    self.children.remove(i);
+                }
+                1 => {
+                    let only = child.children.pop().expect("Checked the length");
+                    child.prefix.extend(only.prefix);
+                    child.terminal = only.terminal;
+                    child.children = only.children;
+                }
+                _ => {}
+            }
+        }
+        true
+    }
+}
+
+fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+/// An exact set of byte strings stored as a compressed trie (Patricia tree).
+///
+/// Chains of single-child nodes are merged into one edge, so the tree has at most one inner node
+/// per stored key. Besides membership it answers prefix queries: longest stored prefix of a key
+/// (route matching) and all keys under a prefix in lexicographic order (autocomplete).
+#[derive(Clone)]
+pub struct RadixTree {
+    root: Node,
+    len: usize,
+}
+
+impl RadixTree {
+    pub fn new() -> Self {
+        RadixTree { root: Node::new(Vec::new(), false), len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Adds `key`, returning whether it was absent.
+    pub fn insert(&mut self, key: &[u8]) -> bool {
+        let inserted = self.root.insert(key);
+        if inserted {
+            self.len += 1;
+        }
+        inserted
+    }
+    pub fn contains(&self, key: &[u8]) -> bool {
+        let mut node = &self.root;
+        let mut rest = key;
+        while !rest.is_empty() {
+            let Ok(i) = node.child(rest[0]) else {
+                return false;
+            };
+            node = &node.children[i];
+            if !rest.starts_with(&node.prefix) {
+                return false;
+            }
+            rest = &rest[node.prefix.len()..];
+        }
+        node.terminal
+    }
+    pub fn remove(&mut self, key: &[u8]) -> bool {
+        let removed = self.root.remove(key);
+        if removed {
+            self.len -= 1;
+        }
+        removed
+    }
+    pub fn clear(&mut self) {
+        *self = RadixTree::new();
+    }
+
+    /// The longest stored key that is a prefix of `key`, if any.
+    pub fn longest_prefix<'k>(&self, key: &'k [u8]) -> Option<&'k [u8]> {
+        let mut node = &self.root;
+        let mut depth = 0;
+        let mut longest = node.terminal.then_some(0);
+        while depth < key.len() {
+            let Ok(i) = node.child(key[depth]) else {
+                break;
+            };
+            node = &node.children[i];
+            if !key[depth..].starts_with(&node.prefix) {
+                break;
+            }
+            depth += node.prefix.len();
+            if node.terminal {
+                longest = Some(depth);
+            }
+        }
+        longest.map(|len| &key[..len])
+    }
+
+    /// All keys in lexicographic order.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter { stack: vec![(&self.root, 0)], key: Vec::new() }
+    }
+    /// The keys starting with `prefix`, in lexicographic order.
+    pub fn starts_with(&self, prefix: &[u8]) -> Iter<'_> {
+        if prefix.is_empty() {
+            return self.iter();
+        }
+        let mut node = &self.root;
+        let mut depth = 0;
+        loop {
+            let Ok(i) = node.child(prefix[depth]) else {
+                return Iter { stack: Vec::new(), key: Vec::new() };
+            };
+            let child = &node.children[i];
+            let common = common_prefix(&child.prefix, &prefix[depth..]);
+            if depth + common == prefix.len() {
+                // The prefix ends inside this edge, so every key below it matches
+                return Iter { stack: vec![(child, depth)], key: prefix[..depth].to_vec() };
+            }
+            if common < child.prefix.len() {
+                return Iter { stack: Vec::new(), key: Vec::new() };
+            }
+            node = child;
+            depth += common;
+        }
+    }
+}
+
+/// A pre-order walk, which visits keys in lexicographic order since children are sorted.
+pub struct Iter<'a> {
+    // Nodes still to visit, with the key length at their parent
+    stack: Vec<(&'a Node, usize)>,
+    key: Vec<u8>,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        while let Some((node, depth)) = self.stack.pop() {
+            self.key.truncate(depth);
+            self.key.extend_from_slice(&node.prefix);
+            let depth = self.key.len();
+            self.stack.extend(node.children.iter().rev().map(|child| (child, depth)));
+            if node.terminal {
+                return Some(self.key.clone());
+            }
+        }
+        None
+    }
+}
+
+impl Default for RadixTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PartialEq for RadixTree {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl Debug for RadixTree {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter().map(|key| String::from_utf8_lossy(&key).into_owned())).finish()
+    }
+}
+
+impl<K: AsRef<[u8]>> FromIterator<K> for RadixTree {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut tree = RadixTree::new();
+        tree.extend(iter);
+        tree
+    }
+}
+
+impl<K: AsRef<[u8]>> Extend<K> for RadixTree {
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        for key in iter {
+            self.insert(key.as_ref());
+        }
+    }
+}
+
+impl Set for RadixTree {
+    type T = Vec<u8>;
+
+    fn insert(&mut self, e: Vec<u8>) {
+        self.insert(&e);
+    }
+    fn contains(&self, e: Vec<u8>) -> bool {
+        self.contains(&e)
+    }
+
+    fn equals(&self, other: Self) -> bool {
+        *self == other
+    }
+
+    fn union(&self, other: Self) -> crate::Result<Self> {
+        let mut union = self.clone();
+        union.extend(other.iter());
+        Ok(union)
+    }
+
+    fn intersection(&self, other: Self) -> crate::Result<Self> {
+        Ok(self.iter().filter(|key| other.contains(key)).collect())
+    }
+
+    fn difference(&self, other: Self) -> crate::Result<Self> {
+        Ok(self.iter().filter(|key| !other.contains(key)).collect())
+    }
+}
+
+impl RemovableSet for RadixTree {
+    fn remove(&mut self, e: Vec<u8>) -> bool {
+        self.remove(&e)
+    }
+    fn clear(&mut self) {
+        self.clear()
+    }
+}
+
+impl EnumerableSet for RadixTree {
+    fn len(&self) -> usize {
+        self.len
+    }
+    fn iter(&self) -> Box<dyn Iterator<Item = Vec<u8>> + '_> {
+        Box::new(self.iter())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use crate::bloom_filter::BloomFilter;
+    use crate::set::conformance::{self, Config};
+    use crate::set::{EnumerableSet, Set};
+    use super::RadixTree;
+
+    fn keys(iter: impl Iterator<Item = Vec<u8>>) -> Vec<String> {
+        iter.map(|key| String::from_utf8(key).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_insert_contains_remove() {
+        let mut tree: RadixTree = ["romane", "romanus", "romulus", "rubens", "ruber"].into_iter().collect();
+        assert_eq!(tree.len(), 5);
+        assert!(!tree.insert(b"romulus"));
+        assert!(tree.contains(b"ruber"));
+        assert!(!tree.contains(b"rom"));
+        assert!(!tree.contains(b"romanesque"));
+
+        assert!(tree.remove(b"romane"));
+        assert!(!tree.remove(b"romane"));
+        assert!(!tree.remove(b"roman"));
+        assert!(tree.contains(b"romanus"));
+        assert_eq!(tree.len(), 4);
+
+        assert!(tree.insert(b""));
+        assert!(tree.contains(b""));
+        assert_eq!(tree.len(), 5);
+    }
+
+    #[test]
+    fn test_random_operations_match_btree_set() {
+        let mut rng = StdRng::seed_from_u64(35);
+        let mut tree = RadixTree::new();
+        let mut expected = BTreeSet::new();
+        for _ in 0..5_000 {
+            let len = rng.gen_range(0..6);
+            let key: Vec<u8> = (0..len).map(|_| rng.gen_range(b'a'..b'd')).collect();
+            if rng.gen_bool(0.6) {
+                assert_eq!(tree.insert(&key), expected.insert(key));
+            } else {
+                assert_eq!(tree.remove(&key), expected.remove(&key));
+            }
+        }
+        assert_eq!(tree.len(), expected.len());
+        assert!(tree.iter().eq(expected.iter().cloned()));
+        for prefix in [&b""[..], b"a", b"ab", b"cab", b"dd"] {
+            let matching = expected.iter().filter(|key| key.starts_with(prefix)).cloned();
+            assert!(tree.starts_with(prefix).eq(matching), "prefix {:?}", prefix);
+        }
+    }
+
+    #[test]
+    fn test_longest_prefix() {
+        let routes: RadixTree = ["/", "/api", "/api/users", "/static"].into_iter().collect();
+        assert_eq!(routes.longest_prefix(b"/api/users/42"), Some(&b"/api/users"[..]));
+        assert_eq!(routes.longest_prefix(b"/api/user"), Some(&b"/api"[..]));
+        assert_eq!(routes.longest_prefix(b"/index.html"), Some(&b"/"[..]));
+        assert_eq!(routes.longest_prefix(b"api"), None);
+    }
+
+    #[test]
+    fn test_starts_with() {
+        let words: RadixTree = ["car", "card", "care", "cart", "cat", "dog"].into_iter().collect();
+        assert_eq!(keys(words.starts_with(b"car")), vec!["car", "card", "care", "cart"]);
+        assert_eq!(keys(words.starts_with(b"ca")), vec!["car", "card", "care", "cart", "cat"]);
+        assert_eq!(keys(words.starts_with(b"cars")), Vec::<String>::new());
+        assert_eq!(keys(words.starts_with(b"x")), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_bloom_filter_pre_check() {
+        let words = ["car", "card", "care"];
+        let tree: RadixTree = words.into_iter().collect();
+        let mut filter = BloomFilter::new(100, 0.01);
+        for word in words {
+            filter.insert(word.to_string());
+        }
+        for word in ["car", "cart", "dog"] {
+            let present = filter.contains(word.to_string()) && tree.contains(word.as_bytes());
+            assert_eq!(present, words.contains(&word));
+        }
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a: RadixTree = ["a", "ab", "abc"].into_iter().collect();
+        let b: RadixTree = ["ab", "abc", "b"].into_iter().collect();
+
+        assert_eq!(keys(a.union(b.clone()).unwrap().iter()), vec!["a", "ab", "abc", "b"]);
+        assert_eq!(keys(a.intersection(b.clone()).unwrap().iter()), vec!["ab", "abc"]);
+        assert_eq!(keys(a.difference(b.clone()).unwrap().iter()), vec!["a"]);
+        assert!(a.symmetric_difference(b).unwrap().equals(["a", "b"].into_iter().collect()));
+    }
+
+    #[test]
+    fn test_conformance() {
+        let config = Config::default();
+        conformance::check_removable_set(&config, RadixTree::new, |id| format!("key/{id}").into_bytes());
+        conformance::check_enumerable_set(&config, RadixTree::new, |id| format!("key/{id}").into_bytes());
+    }
+}
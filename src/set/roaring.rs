@@ -0,0 +1,810 @@
+use std::cmp::Ordering;
+use std::fmt::{Debug, Formatter};
+use crate::bit_vec::BitVec;
+use crate::Error;
+use crate::set::{EnumerableSet, RemovableSet, Set};
+
+// Largest cardinality stored as a sorted array before switching to a bitmap
+const ARRAY_MAX: usize = 4096;
+const BITMAP_BITS: usize = 1 << 16;
+const BITMAP_WORDS: usize = BITMAP_BITS / 64;
+const BITMAP_BYTES: usize = BITMAP_WORDS * 8;
+// Cookies and layout from the portable Roaring serialization format
+const SERIAL_COOKIE_NO_RUNCONTAINER: u32 = 12346;
+const SERIAL_COOKIE: u32 = 12347;
+const NO_OFFSET_THRESHOLD: usize = 4;
+
+// Holds the low 16 bits of every value sharing one set of high 16 bits.
+#[derive(Clone)]
+enum Container {
+    // Sorted, at most ARRAY_MAX values
+    Array(Vec<u16>),
+    // BITMAP_BITS bits, with the number of set bits alongside
+    Bitmap(BitVec, usize),
+    // Sorted, non-adjacent (start, length - 1) pairs
+    Run(Vec<(u16, u16)>),
+}
+
+impl Container {
+    fn len(&self) -> usize {
+        match self {
+            Container::Array(values) => values.len(),
+            Container::Bitmap(_, len) => *len,
+            Container::Run(runs) => runs.iter().map(|&(_, length)| length as usize + 1).sum(),
+        }
+    }
+    fn contains(&self, value: u16) -> bool {
+        match self {
+            Container::Array(values) => values.binary_search(&value).is_ok(),
+            Container::Bitmap(bits, _) => bits.get(value as usize),
+            Container::Run(runs) => {
+                let index = runs.partition_point(|&(start, _)| start <= value);
+                index > 0 && {
+                    let (start, length) = runs[index - 1];
+                    value - start <= length
+                }
+            }
+        }
+    }
+    fn insert(&mut self, value: u16) -> bool {
+        if let Container::Run(_) = self {
+            *self = self.to_plain();
+        }
+        match self {
+            Container::Array(values) => match values.binary_search(&value) {
+                Ok(_) => false,
+                Err(index) => {
+                    values.insert(index, value);
+                    if values.len() > ARRAY_MAX {
+                        *self = Container::Bitmap(self.to_bits(), ARRAY_MAX + 1);
+                    }
+                    true
+                }
+            },
+            Container::Bitmap(bits, len) => {
+                let inserted = !bits.set(value as usize, true);
+                *len += inserted as usize;
+                inserted
+            }
+            Container::Run(_) => unreachable!("Run containers are converted before inserting"),
+        }
+    }
+    fn remove(&mut self, value: u16) -> bool {
+        if let Container::Run(_) = self {
+            *self = self.to_plain();
+        }
+        match self {
+            Container::Array(values) => match values.binary_search(&value) {
+                Ok(index) => {
+                    values.remove(index);
+                    true
+                }
+                Err(_) => false,
+            },
+            Container::Bitmap(bits, len) => {
+                let removed = bits.set(value as usize, false);
+                *len -= removed as usize;
+                if *len <= ARRAY_MAX {
+                    *self = Container::Array(self.iter().collect());
+                }
+                removed
+            }
+            Container::Run(_) => unreachable!("Run containers are converted before removing"),
+        }
+    }
+    fn iter(&self) -> Box<dyn Iterator<Item = u16> + '_> {
+        match self {
+            Container::Array(values) => Box::new(values.iter().copied()),
+            Container::Bitmap(bits, _) => Box::new(bits.iter_ones().map(|i| i as u16)),
+            Container::Run(runs) => Box::new(runs.iter().flat_map(|&(start, length)| start..=start + length)),
+        }
+    }
+    // Number of values strictly less than `value`.
+    fn rank(&self, value: u16) -> usize {
+        match self {
+            Container::Array(values) => values.partition_point(|&v| v < value),
+            Container::Bitmap(bits, _) => bits.rank1(value as usize),
+            Container::Run(runs) => runs.iter()
+                .take_while(|&&(start, _)| start < value)
+                .map(|&(start, length)| ((value - start) as usize).min(length as usize + 1))
+                .sum(),
+        }
+    }
+    fn select(&self, index: usize) -> Option<u16> {
+        match self {
+            Container::Array(values) => values.get(index).copied(),
+            Container::Run(runs) => {
+                let mut remaining = index;
+                for &(start, length) in runs {
+                    if remaining <= length as usize {
+                        return Some(start + remaining as u16);
+                    }
+                    remaining -= length as usize + 1;
+                }
+                None
+            }
+            Container::Bitmap(bits, _) => bits.select1(index).map(|i| i as u16),
+        }
+    }
+
+    fn to_bits(&self) -> BitVec {
+        match self {
+            Container::Bitmap(bits, _) => bits.clone(),
+            _ => {
+                let mut bits = BitVec::new(BITMAP_BITS);
+                for value in self.iter() {
+                    bits.set(value as usize, true);
+                }
+                bits
+            }
+        }
+    }
+    // The same values as an array or bitmap, whichever the cardinality calls for.
+    fn to_plain(&self) -> Container {
+        match self {
+            Container::Run(_) if self.len() <= ARRAY_MAX => Container::Array(self.iter().collect()),
+            Container::Run(_) => Container::Bitmap(self.to_bits(), self.len()),
+            plain => plain.clone(),
+        }
+    }
+    fn to_runs(&self) -> Vec<(u16, u16)> {
+        let mut runs: Vec<(u16, u16)> = Vec::new();
+        for value in self.iter() {
+            match runs.last_mut() {
+                Some((start, length)) if *start as u32 + *length as u32 + 1 == value as u32 => *length += 1,
+                _ => runs.push((value, 0)),
+            }
+        }
+        runs
+    }
+    // Chooses the smallest of the three encodings for these values; None when there are none.
+    fn optimized(self) -> Option<Container> {
+        let len = self.len();
+        if len == 0 {
+            return None;
+        }
+        let runs = self.to_runs();
+        let plain_bytes = if len <= ARRAY_MAX { 2 * len } else { BITMAP_BYTES };
+        if 2 + 4 * runs.len() < plain_bytes {
+            return Some(Container::Run(runs));
+        }
+        Some(self.to_plain())
+    }
+    fn from_bits(bits: BitVec) -> Option<Container> {
+        let len = bits.count_ones();
+        let bitmap = Container::Bitmap(bits, len);
+        if len <= ARRAY_MAX {
+            return Container::Array(bitmap.iter().collect()).optimized();
+        }
+        bitmap.optimized()
+    }
+
+    fn union(&self, other: &Container) -> Container {
+        if let (Container::Array(left), Container::Array(right)) = (self, other) {
+            if left.len() + right.len() <= ARRAY_MAX {
+                let mut merged: Vec<u16> = left.iter().chain(right).copied().collect();
+                merged.sort_unstable();
+                merged.dedup();
+                return Container::Array(merged);
+            }
+        }
+        let mut bits = self.to_bits();
+        bits.or(&other.to_bits()).expect("Containers span the same bits");
+        Container::from_bits(bits).expect("A union of non-empty containers is non-empty")
+    }
+    fn intersection(&self, other: &Container) -> Option<Container> {
+        match (self, other) {
+            (Container::Array(values), other) | (other, Container::Array(values)) => {
+                let kept: Vec<u16> = values.iter().copied().filter(|&v| other.contains(v)).collect();
+                (!kept.is_empty()).then_some(Container::Array(kept))
+            }
+            _ => {
+                let mut bits = self.to_bits();
+                bits.and(&other.to_bits()).expect("Containers span the same bits");
+                Container::from_bits(bits)
+            }
+        }
+    }
+    fn difference(&self, other: &Container) -> Option<Container> {
+        match self {
+            Container::Array(values) => {
+                let kept: Vec<u16> = values.iter().copied().filter(|&v| !other.contains(v)).collect();
+                (!kept.is_empty()).then_some(Container::Array(kept))
+            }
+            _ => {
+                let mut bits = self.to_bits();
+                bits.and_not(&other.to_bits()).expect("Containers span the same bits");
+                Container::from_bits(bits)
+            }
+        }
+    }
+}
+
+/// A compressed set of `u32` values.
+///
+/// Values are grouped by their high 16 bits into containers, each of which picks whichever of a
+/// sorted array, a 65536-bit bitmap or a list of runs is smallest for the values it holds.
+#[derive(Clone, Default)]
+pub struct RoaringBitmap {
+    // Sorted by key, never holding an empty container
+    containers: Vec<(u16, Container)>,
+}
+
+impl RoaringBitmap {
+    pub fn new() -> Self {
+        RoaringBitmap { containers: Vec::new() }
+    }
+
+    pub fn cardinality(&self) -> u64 {
+        self.containers.iter().map(|(_, c)| c.len() as u64).sum()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.containers.is_empty()
+    }
+
+    /// Adds `value`, returning whether it was newly inserted.
+    pub fn insert(&mut self, value: u32) -> bool {
+        let (key, low) = Self::split(value);
+        match self.containers.binary_search_by_key(&key, |(k, _)| *k) {
+            Ok(index) => self.containers[index].1.insert(low),
+            Err(index) => {
+                self.containers.insert(index, (key, Container::Array(vec![low])));
+                true
+            }
+        }
+    }
+    pub fn contains(&self, value: u32) -> bool {
+        let (key, low) = Self::split(value);
+        self.container(key).is_some_and(|c| c.contains(low))
+    }
+    pub fn remove(&mut self, value: u32) -> bool {
+        let (key, low) = Self::split(value);
+        let Ok(index) = self.containers.binary_search_by_key(&key, |(k, _)| *k) else {
+            return false;
+        };
+        let removed = self.containers[index].1.remove(low);
+        if self.containers[index].1.len() == 0 {
+            self.containers.remove(index);
+        }
+        removed
+    }
+    pub fn clear(&mut self) {
+        self.containers.clear();
+    }
+
+    pub fn min(&self) -> Option<u32> {
+        self.iter().next()
+    }
+    pub fn max(&self) -> Option<u32> {
+        let (key, container) = self.containers.last()?;
+        container.select(container.len() - 1).map(|low| Self::join(*key, low))
+    }
+    /// Number of values strictly less than `value`.
+    pub fn rank(&self, value: u32) -> u64 {
+        let (key, low) = Self::split(value);
+        let mut rank = 0;
+        for (k, container) in &self.containers {
+            match k.cmp(&key) {
+                Ordering::Less => rank += container.len() as u64,
+                Ordering::Equal => return rank + container.rank(low) as u64,
+                Ordering::Greater => break,
+            }
+        }
+        rank
+    }
+    /// The value at `index` in ascending order.
+    pub fn select(&self, index: u64) -> Option<u32> {
+        let mut remaining = index;
+        for (key, container) in &self.containers {
+            let len = container.len() as u64;
+            if remaining < len {
+                return container.select(remaining as usize).map(|low| Self::join(*key, low));
+            }
+            remaining -= len;
+        }
+        None
+    }
+    /// Iterates in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        self.containers.iter().flat_map(|(key, container)| container.iter().map(|low| Self::join(*key, low)))
+    }
+
+    /// Re-encodes every container as runs wherever that is smaller, returning whether any changed.
+    pub fn run_optimize(&mut self) -> bool {
+        let mut changed = false;
+        for (_, container) in self.containers.iter_mut() {
+            let was_run = matches!(container, Container::Run(_));
+            let optimized = container.clone().optimized().expect("Containers are never empty");
+            changed |= was_run != matches!(optimized, Container::Run(_));
+            *container = optimized;
+        }
+        changed
+    }
+
+    /// Writes the set in the portable Roaring format shared by the C, Java and Go libraries.
+    pub fn serialize(&self) -> Vec<u8> {
+        let size = self.containers.len();
+        let has_runs = self.containers.iter().any(|(_, c)| matches!(c, Container::Run(_)));
+        let mut bytes = Vec::new();
+        if has_runs {
+            bytes.extend_from_slice(&(SERIAL_COOKIE | ((size as u32 - 1) << 16)).to_le_bytes());
+            let mut run_flags = vec![0u8; size.div_ceil(8)];
+            for (i, (_, container)) in self.containers.iter().enumerate() {
+                if let Container::Run(_) = container {
+                    run_flags[i / 8] |= 1 << (i % 8);
+                }
+            }
+            bytes.extend_from_slice(&run_flags);
+        } else {
+            bytes.extend_from_slice(&SERIAL_COOKIE_NO_RUNCONTAINER.to_le_bytes());
+            bytes.extend_from_slice(&(size as u32).to_le_bytes());
+        }
+        for (key, container) in &self.containers {
+            bytes.extend_from_slice(&key.to_le_bytes());
+            bytes.extend_from_slice(&((container.len() - 1) as u16).to_le_bytes());
+        }
+        if !has_runs || size >= NO_OFFSET_THRESHOLD {
+            let mut offset = bytes.len() + 4 * size;
+            for (_, container) in &self.containers {
+                bytes.extend_from_slice(&(offset as u32).to_le_bytes());
+                offset += Self::container_bytes(container);
+            }
+        }
+        for (_, container) in &self.containers {
+            match container {
+                Container::Array(values) => values.iter().for_each(|v| bytes.extend_from_slice(&v.to_le_bytes())),
+                Container::Bitmap(bits, _) => bits.words().iter().for_each(|w| bytes.extend_from_slice(&w.to_le_bytes())),
+                Container::Run(runs) => {
+                    bytes.extend_from_slice(&(runs.len() as u16).to_le_bytes());
+                    for (start, length) in runs {
+                        bytes.extend_from_slice(&start.to_le_bytes());
+                        bytes.extend_from_slice(&length.to_le_bytes());
+                    }
+                }
+            }
+        }
+        bytes
+    }
+    /// Reads a set written by `serialize` or any other portable Roaring implementation.
+    pub fn deserialize(bytes: &[u8]) -> crate::Result<Self> {
+        let mut reader = Reader { bytes, position: 0 };
+        let cookie = reader.u32()?;
+        let (size, run_flags) = if cookie & 0xFFFF == SERIAL_COOKIE {
+            let size = (cookie >> 16) as usize + 1;
+            (size, reader.take(size.div_ceil(8))?.to_vec())
+        } else if cookie == SERIAL_COOKIE_NO_RUNCONTAINER {
+            (reader.u32()? as usize, Vec::new())
+        } else {
+            return Err(Error::IllegalArguments(format!("Unknown Roaring cookie {cookie}.")));
+        };
+        // Each container has a 4-byte header, so a size the input can't hold is rejected before
+        // anything is allocated for it
+        if size > 1 << 16 || 4 * size > reader.remaining() {
+            return Err(Error::IllegalArguments(format!("Roaring data can't hold {size} containers.")));
+        }
+        let is_run = |i: usize| run_flags.get(i / 8).is_some_and(|flags| flags & (1 << (i % 8)) != 0);
+
+        let mut header = Vec::with_capacity(size);
+        for _ in 0..size {
+            header.push((reader.u16()?, reader.u16()? as usize + 1));
+        }
+        if run_flags.is_empty() || size >= NO_OFFSET_THRESHOLD {
+            // Containers are stored back to back, so the offsets carry nothing we need
+            reader.take(4 * size)?;
+        }
+
+        let mut containers: Vec<(u16, Container)> = Vec::with_capacity(size);
+        for (i, (key, len)) in header.into_iter().enumerate() {
+            if containers.last().is_some_and(|(last, _)| *last >= key) {
+                return Err(Error::IllegalArguments(String::from("Roaring container keys must ascend.")));
+            }
+            let container = if is_run(i) {
+                let num_runs = reader.u16()?;
+                let mut runs = Vec::with_capacity(num_runs as usize);
+                for _ in 0..num_runs {
+                    let (start, length) = (reader.u16()?, reader.u16()?);
+                    if start as u32 + length as u32 > u16::MAX as u32 {
+                        return Err(Error::IllegalArguments(format!("A run in Roaring container {key} ends past 65535.")));
+                    }
+                    // Each run must start past the end of the one before it, with a gap between them
+                    if let Some(&(last, last_length)) = runs.last() {
+                        if start as u32 <= last as u32 + last_length as u32 + 1 {
+                            return Err(Error::IllegalArguments(format!("Runs in Roaring container {key} must ascend without touching.")));
+                        }
+                    }
+                    runs.push((start, length));
+                }
+                Container::Run(runs)
+            } else if len <= ARRAY_MAX {
+                let mut values = Vec::with_capacity(len);
+                for _ in 0..len {
+                    values.push(reader.u16()?);
+                }
+                if values.windows(2).any(|pair| pair[0] >= pair[1]) {
+                    return Err(Error::IllegalArguments(format!("Values in Roaring container {key} must strictly ascend.")));
+                }
+                Container::Array(values)
+            } else {
+                let mut words = Vec::with_capacity(BITMAP_WORDS);
+                for _ in 0..BITMAP_WORDS {
+                    words.push(reader.u64()?);
+                }
+                // Count the bits rather than trust the header, which the check below compares with
+                let bits = BitVec::from_words(words, BITMAP_BITS)?;
+                let ones = bits.count_ones();
+                Container::Bitmap(bits, ones)
+            };
+            if container.len() != len {
+                return Err(Error::IllegalArguments(format!("Roaring container {key} has the wrong cardinality.")));
+            }
+            containers.push((key, container));
+        }
+        Ok(RoaringBitmap { containers })
+    }
+
+    fn split(value: u32) -> (u16, u16) {
+        ((value >> 16) as u16, value as u16)
+    }
+    fn join(key: u16, low: u16) -> u32 {
+        (key as u32) << 16 | low as u32
+    }
+    fn container(&self, key: u16) -> Option<&Container> {
+        self.containers.binary_search_by_key(&key, |(k, _)| *k).ok().map(|index| &self.containers[index].1)
+    }
+    fn container_bytes(container: &Container) -> usize {
+        match container {
+            Container::Array(values) => 2 * values.len(),
+            Container::Bitmap(_, _) => BITMAP_BYTES,
+            Container::Run(runs) => 2 + 4 * runs.len(),
+        }
+    }
+    // Walks both sets' containers in key order, combining those with matching keys.
+    fn merge<F, G>(&self, other: &Self, keep_unmatched: (bool, bool), combine: F, map: G) -> Self
+        where
+            F: Fn(&Container, &Container) -> Option<Container>,
+            G: Fn(&Container) -> Container,
+    {
+        let mut containers = Vec::new();
+        let (mut left, mut right) = (self.containers.iter().peekable(), other.containers.iter().peekable());
+        loop {
+            match (left.peek(), right.peek()) {
+                (None, None) => break,
+                (Some((key, container)), None) => {
+                    if keep_unmatched.0 {
+                        containers.push((*key, map(container)));
+                    }
+                    left.next();
+                }
+                (None, Some((key, container))) => {
+                    if keep_unmatched.1 {
+                        containers.push((*key, map(container)));
+                    }
+                    right.next();
+                }
+                (Some((l_key, l)), Some((r_key, r))) => match l_key.cmp(r_key) {
+                    Ordering::Less => {
+                        if keep_unmatched.0 {
+                            containers.push((*l_key, map(l)));
+                        }
+                        left.next();
+                    }
+                    Ordering::Greater => {
+                        if keep_unmatched.1 {
+                            containers.push((*r_key, map(r)));
+                        }
+                        right.next();
+                    }
+                    Ordering::Equal => {
+                        if let Some(combined) = combine(l, r) {
+                            containers.push((*l_key, combined));
+                        }
+                        left.next();
+                        right.next();
+                    }
+                },
+            }
+        }
+        RoaringBitmap { containers }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+    fn take(&mut self, len: usize) -> crate::Result<&'a [u8]> {
+        let end = self.position + len;
+        if end > self.bytes.len() {
+            return Err(Error::IllegalArguments(String::from("Roaring data ended early.")));
+        }
+        let taken = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(taken)
+    }
+    fn u16(&mut self) -> crate::Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().expect("Took two bytes")))
+    }
+    fn u32(&mut self) -> crate::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().expect("Took four bytes")))
+    }
+    fn u64(&mut self) -> crate::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().expect("Took eight bytes")))
+    }
+}
+
+impl Debug for RoaringBitmap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl FromIterator<u32> for RoaringBitmap {
+    fn from_iter<I: IntoIterator<Item = u32>>(iter: I) -> Self {
+        let mut bitmap = RoaringBitmap::new();
+        bitmap.extend(iter);
+        bitmap
+    }
+}
+
+impl Extend<u32> for RoaringBitmap {
+    fn extend<I: IntoIterator<Item = u32>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl Set for RoaringBitmap {
+    type T = u32;
+
+    fn insert(&mut self, e: u32) {
+        self.insert(e);
+    }
+    fn contains(&self, e: u32) -> bool {
+        self.contains(e)
+    }
+
+    fn equals(&self, other: Self) -> bool {
+        self.containers.len() == other.containers.len()
+            && self.containers.iter().zip(&other.containers).all(|((l_key, l), (r_key, r))| {
+                l_key == r_key && l.len() == r.len() && l.iter().eq(r.iter())
+            })
+    }
+
+    fn union(&self, other: Self) -> crate::Result<Self> {
+        Ok(self.merge(&other, (true, true), |l, r| Some(l.union(r)), Container::clone))
+    }
+
+    fn intersection(&self, other: Self) -> crate::Result<Self> {
+        Ok(self.merge(&other, (false, false), Container::intersection, Container::clone))
+    }
+
+    fn difference(&self, other: Self) -> crate::Result<Self> {
+        Ok(self.merge(&other, (true, false), Container::difference, Container::clone))
+    }
+}
+
+impl RemovableSet for RoaringBitmap {
+    fn remove(&mut self, e: u32) -> bool {
+        self.remove(e)
+    }
+    fn clear(&mut self) {
+        self.clear()
+    }
+}
+
+impl EnumerableSet for RoaringBitmap {
+    fn len(&self) -> usize {
+        self.cardinality() as usize
+    }
+    fn iter(&self) -> Box<dyn Iterator<Item = u32> + '_> {
+        Box::new(self.iter())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use crate::set::conformance::{self, Config};
+    use crate::set::{EnumerableSet, Set};
+    use super::{Container, RoaringBitmap, BITMAP_WORDS, SERIAL_COOKIE, SERIAL_COOKIE_NO_RUNCONTAINER};
+
+    fn clustered(seed: u64) -> (RoaringBitmap, BTreeSet<u32>) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut expected = BTreeSet::new();
+        // A sparse chunk, a dense chunk and a long run
+        for _ in 0..1_000 {
+            expected.insert(rng.gen_range(0..65_536));
+        }
+        for _ in 0..10_000 {
+            expected.insert(rng.gen_range(1 << 16..2 << 16));
+        }
+        expected.extend((5 << 16) + 100..(5 << 16) + 20_000);
+        (expected.iter().copied().collect(), expected)
+    }
+
+    #[test]
+    fn test_insert_contains_remove() {
+        let mut bitmap = RoaringBitmap::new();
+        assert!(bitmap.insert(7));
+        assert!(!bitmap.insert(7));
+        assert!(bitmap.insert(1 << 20));
+        assert!(bitmap.insert(u32::MAX));
+        assert_eq!(bitmap.cardinality(), 3);
+        assert!(bitmap.contains(1 << 20));
+        assert!(!bitmap.contains(8));
+
+        assert!(bitmap.remove(1 << 20));
+        assert!(!bitmap.remove(1 << 20));
+        assert_eq!(bitmap.iter().collect::<Vec<_>>(), vec![7, u32::MAX]);
+        assert_eq!(bitmap.min(), Some(7));
+        assert_eq!(bitmap.max(), Some(u32::MAX));
+    }
+
+    #[test]
+    fn test_container_transitions() {
+        let mut bitmap: RoaringBitmap = (0..5_000).map(|i| i * 2).collect();
+        assert!(matches!(bitmap.containers[0].1, Container::Bitmap(_, 5_000)));
+
+        for i in 0..1_000 {
+            bitmap.remove(i * 2);
+        }
+        assert!(matches!(bitmap.containers[0].1, Container::Array(_)));
+        assert_eq!(bitmap.cardinality(), 4_000);
+
+        let mut runs: RoaringBitmap = (100..60_000).collect();
+        assert!(runs.run_optimize());
+        assert!(matches!(runs.containers[0].1, Container::Run(_)));
+        assert!(runs.contains(100) && runs.contains(59_999) && !runs.contains(60_000));
+        assert_eq!(runs.rank(60_000), 59_900);
+
+        let mut full: RoaringBitmap = (0..1 << 17).collect();
+        full.run_optimize();
+        assert_eq!(full.rank(1 << 17), 1 << 17);
+        assert_eq!(full.select((1 << 17) - 1), Some((1 << 17) - 1));
+        runs.insert(70_000 - 65_536);
+        assert_eq!(runs.cardinality(), 59_900);
+    }
+
+    #[test]
+    fn test_random_operations_match_btree_set() {
+        let (mut bitmap, mut expected) = clustered(30);
+        let mut rng = StdRng::seed_from_u64(31);
+        for _ in 0..20_000 {
+            let value = rng.gen_range(0..6 << 16);
+            if rng.gen_bool(0.5) {
+                assert_eq!(bitmap.insert(value), expected.insert(value));
+            } else {
+                assert_eq!(bitmap.remove(value), expected.remove(&value));
+            }
+        }
+        bitmap.run_optimize();
+        assert_eq!(bitmap.cardinality(), expected.len() as u64);
+        assert!(bitmap.iter().eq(expected.iter().copied()));
+        for value in [0, 500, 70_000, (5 << 16) + 150, 6 << 16] {
+            assert_eq!(bitmap.rank(value), expected.range(..value).count() as u64);
+        }
+        for index in [0, 10, 5_000, expected.len() as u64 - 1] {
+            assert_eq!(bitmap.select(index), expected.iter().nth(index as usize).copied());
+        }
+        assert_eq!(bitmap.select(expected.len() as u64), None);
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let (a, expected_a) = clustered(1);
+        let (b, expected_b) = clustered(2);
+
+        let union = a.union(b.clone()).unwrap();
+        assert!(union.iter().eq(expected_a.union(&expected_b).copied()));
+
+        let intersection = a.intersection(b.clone()).unwrap();
+        assert!(intersection.iter().eq(expected_a.intersection(&expected_b).copied()));
+
+        let difference = a.difference(b.clone()).unwrap();
+        assert!(difference.iter().eq(expected_a.difference(&expected_b).copied()));
+
+        let symmetric_difference = a.symmetric_difference(b.clone()).unwrap();
+        assert!(symmetric_difference.iter().eq(expected_a.symmetric_difference(&expected_b).copied()));
+
+        assert!(a.equals(a.clone()));
+        assert!(!a.equals(b));
+    }
+
+    #[test]
+    fn test_serialize_without_runs() {
+        let bitmap: RoaringBitmap = [1, 2, 1 << 16].into_iter().collect();
+        let bytes = bitmap.serialize();
+        // Cookie, size, two key/cardinality pairs, two offsets, three values
+        assert_eq!(bytes.len(), 4 + 4 + 2 * 4 + 2 * 4 + 3 * 2);
+        assert_eq!(&bytes[..8], &[0x3A, 0x30, 0, 0, 2, 0, 0, 0]);
+        assert!(RoaringBitmap::deserialize(&bytes).unwrap().equals(bitmap));
+    }
+
+    #[test]
+    fn test_serialize_round_trip() {
+        let (mut bitmap, _) = clustered(3);
+        bitmap.run_optimize();
+        let restored = RoaringBitmap::deserialize(&bitmap.serialize()).unwrap();
+        assert!(restored.iter().eq(bitmap.iter()));
+
+        let bytes = bitmap.serialize();
+        assert!(RoaringBitmap::deserialize(&bytes[..bytes.len() - 1]).is_err());
+        assert!(RoaringBitmap::deserialize(&[0, 0, 0, 0]).is_err());
+    }
+
+    // One run container under key 0, in the format `serialize` writes.
+    fn run_container_bytes(runs: &[(u16, u16)]) -> Vec<u8> {
+        let cardinality: usize = runs.iter().map(|&(_, length)| length as usize + 1).sum();
+        let mut bytes = SERIAL_COOKIE.to_le_bytes().to_vec();
+        bytes.push(1);
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+        bytes.extend_from_slice(&((cardinality - 1) as u16).to_le_bytes());
+        bytes.extend_from_slice(&(runs.len() as u16).to_le_bytes());
+        for (start, length) in runs {
+            bytes.extend_from_slice(&start.to_le_bytes());
+            bytes.extend_from_slice(&length.to_le_bytes());
+        }
+        bytes
+    }
+
+    // One array or bitmap container under key 0 claiming `cardinality` values.
+    fn plain_container_bytes(cardinality: usize, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = SERIAL_COOKIE_NO_RUNCONTAINER.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+        bytes.extend_from_slice(&((cardinality - 1) as u16).to_le_bytes());
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    #[test]
+    fn test_deserialize_rejects_malformed_containers() {
+        let valid = RoaringBitmap::deserialize(&run_container_bytes(&[(10, 5), (20, 1)])).unwrap();
+        assert!(valid.contains(15) && !valid.contains(16) && valid.contains(21));
+        // A run past 65535, overlapping, adjacent and unsorted runs
+        assert!(RoaringBitmap::deserialize(&run_container_bytes(&[(65535, 5)])).is_err());
+        assert!(RoaringBitmap::deserialize(&run_container_bytes(&[(10, 5), (12, 3)])).is_err());
+        assert!(RoaringBitmap::deserialize(&run_container_bytes(&[(10, 5), (16, 1)])).is_err());
+        assert!(RoaringBitmap::deserialize(&run_container_bytes(&[(20, 1), (10, 1)])).is_err());
+
+        let array = |values: &[u16]| values.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>();
+        assert!(RoaringBitmap::deserialize(&plain_container_bytes(2, &array(&[3, 5]))).is_ok());
+        assert!(RoaringBitmap::deserialize(&plain_container_bytes(2, &array(&[5, 3]))).is_err());
+        assert!(RoaringBitmap::deserialize(&plain_container_bytes(2, &array(&[3, 3]))).is_err());
+
+        // A bitmap whose header claims more values than it has bits set
+        let mut words = vec![0u64; BITMAP_WORDS];
+        words[..65].iter_mut().for_each(|word| *word = u64::MAX);
+        let bitmap: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+        assert!(RoaringBitmap::deserialize(&plain_container_bytes(65 * 64, &bitmap)).is_ok());
+        assert!(RoaringBitmap::deserialize(&plain_container_bytes(5_000, &bitmap)).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_rejects_huge_container_count() {
+        let mut bytes = SERIAL_COOKIE_NO_RUNCONTAINER.to_le_bytes().to_vec();
+        bytes.extend(u32::MAX.to_le_bytes());
+        assert!(RoaringBitmap::deserialize(&bytes).is_err());
+        // Within 65536, but more headers than the bytes that follow could hold
+        let mut bytes = SERIAL_COOKIE_NO_RUNCONTAINER.to_le_bytes().to_vec();
+        bytes.extend(1_000u32.to_le_bytes());
+        bytes.extend([0; 64]);
+        assert!(RoaringBitmap::deserialize(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_conformance() {
+        let config = Config::default();
+        conformance::check_removable_set(&config, RoaringBitmap::new, |id| id as u32 * 37);
+        conformance::check_enumerable_set(&config, RoaringBitmap::new, |id| id as u32 * 37);
+    }
+}
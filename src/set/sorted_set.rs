@@ -0,0 +1,345 @@
+use std::cmp::Ordering;
+use std::fmt::{Debug, Formatter};
+use std::ops::RangeBounds;
+use crate::map::sorted_map::{self, SortedMap};
+use crate::set::{EnumerableSet, RemovableSet, Set};
+
+/// An exact ordered set backed by the skip list of a `SortedMap` with unit values.
+pub struct SortedSet<T: Ord> {
+    map: SortedMap<T, ()>,
+}
+
+impl<T: Ord> SortedSet<T> {
+    pub fn new() -> Self {
+        SortedSet { map: SortedMap::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Adds `value`, returning whether it was absent.
+    pub fn insert(&mut self, value: T) -> bool {
+        self.map.insert(value, ()).is_none()
+    }
+    pub fn contains(&self, value: &T) -> bool {
+        self.map.contains_key(value)
+    }
+    pub fn get(&self, value: &T) -> Option<&T> {
+        self.map.get_key_value(value).map(|(value, _)| value)
+    }
+    pub fn remove(&mut self, value: &T) -> bool {
+        self.map.remove(value).is_some()
+    }
+    pub fn take(&mut self, value: &T) -> Option<T> {
+        self.map.remove_entry(value).map(|(value, _)| value)
+    }
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.map.first_key_value().map(|(value, _)| value)
+    }
+    pub fn last(&self) -> Option<&T> {
+        self.map.last_key_value().map(|(value, _)| value)
+    }
+    pub fn pop_first(&mut self) -> Option<T> {
+        self.map.pop_first().map(|(value, _)| value)
+    }
+    pub fn pop_last(&mut self) -> Option<T> {
+        self.map.pop_last().map(|(value, _)| value)
+    }
+    /// The greatest element less than or equal to `value`.
+    pub fn floor(&self, value: &T) -> Option<&T> {
+        self.map.floor(value).map(|(value, _)| value)
+    }
+    /// The least element greater than or equal to `value`.
+    pub fn ceiling(&self, value: &T) -> Option<&T> {
+        self.map.ceiling(value).map(|(value, _)| value)
+    }
+    /// Number of elements strictly less than `value`.
+    pub fn rank(&self, value: &T) -> usize {
+        self.map.rank(value)
+    }
+    /// The element at `index` in ascending order.
+    pub fn select(&self, index: usize) -> Option<&T> {
+        self.map.select(index).map(|(value, _)| value)
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { inner: self.map.iter() }
+    }
+    pub fn range<R: RangeBounds<T>>(&self, range: R) -> Iter<'_, T> {
+        Iter { inner: self.map.range(range) }
+    }
+
+    fn from_sorted<I: IntoIterator<Item = T>>(values: I) -> Self {
+        SortedSet { map: SortedMap::from_sorted(values.into_iter().map(|value| (value, ()))) }
+    }
+}
+
+impl<T: Ord + Clone> SortedSet<T> {
+    // Walks both sets in order, keeping the values `keep` selects from (in self, in other).
+    fn merge<F: Fn(bool, bool) -> bool>(&self, other: &Self, keep: F) -> Self {
+        let mut merged = Vec::new();
+        let mut left = self.iter().peekable();
+        let mut right = other.iter().peekable();
+        loop {
+            let (value, in_left, in_right) = match (left.peek(), right.peek()) {
+                (None, None) => break,
+                (Some(_), None) => (left.next(), true, false),
+                (None, Some(_)) => (right.next(), false, true),
+                (Some(l), Some(r)) => match l.cmp(r) {
+                    Ordering::Less => (left.next(), true, false),
+                    Ordering::Greater => (right.next(), false, true),
+                    Ordering::Equal => {
+                        right.next();
+                        (left.next(), true, true)
+                    }
+                },
+            };
+            if keep(in_left, in_right) {
+                merged.extend(value.cloned());
+            }
+        }
+        SortedSet::from_sorted(merged)
+    }
+}
+
+pub struct Iter<'a, T: Ord> {
+    inner: sorted_map::Iter<'a, T, ()>,
+}
+
+impl<'a, T: Ord> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.inner.next().map(|(value, _)| value)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T: Ord> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(value, _)| value)
+    }
+}
+
+impl<T: Ord> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T: Ord> From<SortedMap<T, ()>> for SortedSet<T> {
+    fn from(map: SortedMap<T, ()>) -> Self {
+        SortedSet { map }
+    }
+}
+
+impl<T: Ord> Default for SortedSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord + Clone> Clone for SortedSet<T> {
+    fn clone(&self) -> Self {
+        SortedSet::from_sorted(self.iter().cloned())
+    }
+}
+
+impl<T: Ord + Debug> Debug for SortedSet<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Ord> FromIterator<T> for SortedSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut values: Vec<T> = iter.into_iter().collect();
+        values.sort();
+        values.dedup();
+        SortedSet::from_sorted(values)
+    }
+}
+
+impl<T: Ord> Extend<T> for SortedSet<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl<T: Ord + Clone> Set for SortedSet<T> {
+    type T = T;
+
+    fn insert(&mut self, e: T) {
+        self.insert(e);
+    }
+    fn contains(&self, e: T) -> bool {
+        self.contains(&e)
+    }
+
+    fn equals(&self, other: Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+
+    fn union(&self, other: Self) -> crate::Result<Self> {
+        Ok(self.merge(&other, |in_left, in_right| in_left || in_right))
+    }
+
+    fn intersection(&self, other: Self) -> crate::Result<Self> {
+        Ok(self.merge(&other, |in_left, in_right| in_left && in_right))
+    }
+
+    fn difference(&self, other: Self) -> crate::Result<Self> {
+        Ok(self.merge(&other, |in_left, in_right| in_left && !in_right))
+    }
+}
+
+impl<T: Ord + Clone> RemovableSet for SortedSet<T> {
+    fn remove(&mut self, e: T) -> bool {
+        self.remove(&e)
+    }
+    fn clear(&mut self) {
+        self.clear()
+    }
+}
+
+impl<T: Ord + Clone> EnumerableSet for SortedSet<T> {
+    fn len(&self) -> usize {
+        self.map.len()
+    }
+    fn iter(&self) -> Box<dyn Iterator<Item = T> + '_> {
+        Box::new(self.iter().cloned())
+    }
+    fn symmetric_difference(&self, other: Self) -> crate::Result<Self> {
+        Ok(self.merge(&other, |in_left, in_right| in_left != in_right))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use crate::set::conformance::{self, Config};
+    use crate::set::{EnumerableSet, Set};
+    use super::SortedSet;
+
+    #[test]
+    fn test_insert_contains_remove() {
+        let mut set: SortedSet<u32> = SortedSet::new();
+        assert!(set.insert(5));
+        assert!(set.insert(1));
+        assert!(set.insert(3));
+        assert!(!set.insert(3));
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.iter().copied().collect::<Vec<_>>(), vec![1, 3, 5]);
+
+        assert!(set.remove(&3));
+        assert!(!set.remove(&3));
+        assert!(!set.contains(&3));
+        assert_eq!(set.iter().rev().copied().collect::<Vec<_>>(), vec![5, 1]);
+    }
+
+    #[test]
+    fn test_random_operations_match_btree_set() {
+        let mut rng = StdRng::seed_from_u64(29);
+        let mut set: SortedSet<u32> = SortedSet::new();
+        let mut expected: BTreeSet<u32> = BTreeSet::new();
+        for _ in 0..5_000 {
+            let value = rng.gen_range(0..500);
+            if rng.gen_bool(0.6) {
+                assert_eq!(set.insert(value), expected.insert(value));
+            } else {
+                assert_eq!(set.remove(&value), expected.remove(&value));
+            }
+        }
+        assert_eq!(set.len(), expected.len());
+        assert!(set.iter().eq(expected.iter()));
+        assert!(set.iter().rev().eq(expected.iter().rev()));
+        for (index, value) in expected.iter().enumerate() {
+            assert_eq!(set.select(index), Some(value));
+            assert_eq!(set.rank(value), index);
+        }
+        assert_eq!(set.select(expected.len()), None);
+    }
+
+    #[test]
+    fn test_ordered_queries() {
+        let set: SortedSet<u32> = [10, 20, 30, 40].into_iter().collect();
+        assert_eq!(set.first(), Some(&10));
+        assert_eq!(set.last(), Some(&40));
+        assert_eq!(set.floor(&25), Some(&20));
+        assert_eq!(set.floor(&20), Some(&20));
+        assert_eq!(set.floor(&5), None);
+        assert_eq!(set.ceiling(&25), Some(&30));
+        assert_eq!(set.ceiling(&30), Some(&30));
+        assert_eq!(set.ceiling(&45), None);
+        assert_eq!(set.rank(&25), 2);
+        assert_eq!(set.select(3), Some(&40));
+    }
+
+    #[test]
+    fn test_range() {
+        let set: SortedSet<u32> = (0..20).map(|i| i * 5).collect();
+        let expected: BTreeSet<u32> = (0..20).map(|i| i * 5).collect();
+
+        assert!(set.range(12..43).eq(expected.range(12..43)));
+        assert!(set.range(15..=45).eq(expected.range(15..=45)));
+        assert!(set.range(..=7).eq(expected.range(..=7)));
+        assert!(set.range(90..).eq(expected.range(90..)));
+        assert!(set.range(200..).eq(expected.range(200..)));
+        assert!(set.range(12..43).rev().eq(expected.range(12..43).rev()));
+        assert_eq!(set.range(12..43).len(), 6);
+
+        let mut range = set.range(10..=30);
+        assert_eq!(range.next(), Some(&10));
+        assert_eq!(range.next_back(), Some(&30));
+        assert_eq!(range.collect::<Vec<_>>(), vec![&15, &20, &25]);
+    }
+
+    #[test]
+    fn test_pop_first_and_last() {
+        let mut set: SortedSet<u32> = (1..=5).collect();
+        assert_eq!(set.pop_first(), Some(1));
+        assert_eq!(set.pop_last(), Some(5));
+        assert_eq!(set.iter().copied().collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert_eq!(set.last(), Some(&4));
+        set.clear();
+        assert_eq!(set.pop_last(), None);
+        assert_eq!(set.pop_first(), None);
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a: SortedSet<u32> = [1, 2, 3, 4].into_iter().collect();
+        let b: SortedSet<u32> = [3, 4, 5].into_iter().collect();
+
+        let union = a.union(b.clone()).unwrap();
+        assert!(union.equals((1..=5).collect()));
+        assert_eq!(union.select(4), Some(&5));
+
+        let intersection = a.intersection(b.clone()).unwrap();
+        assert!(intersection.equals([3, 4].into_iter().collect()));
+
+        let difference = a.difference(b.clone()).unwrap();
+        assert!(difference.equals([1, 2].into_iter().collect()));
+
+        let symmetric_difference = a.symmetric_difference(b).unwrap();
+        assert!(symmetric_difference.equals([1, 2, 5].into_iter().collect()));
+        assert_eq!(symmetric_difference.last(), Some(&5));
+    }
+
+    #[test]
+    fn test_conformance() {
+        let config = Config::default();
+        conformance::check_removable_set(&config, SortedSet::new, |id| id);
+        conformance::check_enumerable_set(&config, SortedSet::new, |id| id);
+    }
+}